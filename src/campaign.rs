@@ -0,0 +1,319 @@
+//! Campaign files describing a whole run of auten.
+//!
+//! A campaign is a TOML file of top-level keys, an `[output]` table and arrays
+//! of `[[symbol]]` and `[[run]]` tables. For example:
+//!
+//! ```toml
+//! target = "x86_64-unknown-linux-gnu"
+//! ops = ["cmp", "+"]
+//! strategies = ["boundary", "random", "deep"]
+//! seed = 42
+//! depth = 4
+//!
+//! [output]
+//! path = "constant-folding-gen.c"
+//! ub = "split"
+//!
+//! [[symbol]]
+//! name = "a"
+//! type = "int"
+//!
+//! [[run]]
+//! support-symbolic-integer-casts = true
+//! ```
+//!
+//! Without `[[symbol]]` tables the symbols of set_of_syms are used. Relative
+//! output paths are relative to the directory of the campaign file, so that a
+//! campaign writes the same files from wherever it is run.
+
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use serde::Deserialize;
+use toml::{Spanned, Table, Value};
+
+use symi::BinOp::Opcode;
+use symi::DataType::CDataTypes;
+use symi::Strategy::Registry;
+use symi::Symbol::Sym;
+use symi::Target::TargetInfo;
+
+use crate::cli::*;
+use crate::{Format, Options};
+
+/// The keys of a campaign file, checked by parse_campaign.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Campaign {
+    ops: Option<Spanned<Strings>>,
+    strategies: Option<Spanned<Strings>>,
+    seed: Option<u64>,
+    depth: Option<usize>,
+    target: Option<Spanned<String>>,
+    types: Option<Vec<Spanned<String>>>,
+    #[serde(default)]
+    output: Output,
+    #[serde(default)]
+    symbol: Vec<Symbol>,
+    /// analyzer-config options of each RUN line.
+    #[serde(default)]
+    run: Vec<Table>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Output {
+    path: Option<String>,
+    ub_path: Option<String>,
+    format: Option<Spanned<String>>,
+    parens: Option<Spanned<String>>,
+    ub: Option<Spanned<String>>,
+    infeasible: Option<Spanned<String>>,
+    directives: Option<Spanned<String>>,
+    force: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Symbol {
+    name: Spanned<String>,
+    #[serde(rename = "type")]
+    ty: Spanned<String>,
+}
+
+/// A string, or an array of strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Strings {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Strings {
+    /// The strings joined with spaces.
+    fn join(&self) -> String {
+        match self {
+            Strings::One(s) => s.clone(),
+            Strings::Many(strs) => strs.join(" "),
+        }
+    }
+}
+
+/// An error in a campaign file, at a line of it if not 0.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CampaignError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for CampaignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{}", self.msg),
+            line => write!(f, "line {}: {}", line, self.msg),
+        }
+    }
+}
+
+/// The line of `text` the span starts on.
+fn lineOf(text: &str, span: Range<usize>) -> usize {
+    text[..span.start].matches('\n').count() + 1
+}
+
+fn error<T>(line: usize, msg: String) -> Result<T, CampaignError> {
+    Err(CampaignError { line, msg })
+}
+
+/// Check `val` with `parse`, which fails for invalid values.
+fn expectWith<T>(
+    text: &str,
+    val: &Spanned<String>,
+    key: &str,
+    parse: fn(&str) -> Option<T>,
+) -> Result<T, CampaignError> {
+    match parse(val.get_ref()) {
+        Some(parsed) => Ok(parsed),
+        None => error(
+            lineOf(text, val.span()),
+            format!("invalid value '{}' of {}", val.get_ref(), key),
+        ),
+    }
+}
+
+fn isIdentifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns the operators and options of the campaign in `text`. Relative
+/// output paths are resolved against `dir`.
+pub(crate) fn parse_campaign(
+    text: &str,
+    dir: &Path,
+) -> Result<(Vec<Opcode>, Options), CampaignError> {
+    let campaign: Campaign = toml::from_str(text).map_err(|err| CampaignError {
+        line: err.span().map_or(0, |span| lineOf(text, span)),
+        msg: err.message().to_string(),
+    })?;
+    let mut opts = Options::default();
+
+    let Some(ops) = campaign.ops else {
+        return error(0, "a campaign needs ops".to_string());
+    };
+    let names = ops.get_ref().join();
+    let Some(ops) = parse_ops(&names) else {
+        return error(
+            lineOf(text, ops.span()),
+            format!("invalid value '{}' of ops", names),
+        );
+    };
+
+    if let Some(strategies) = campaign.strategies {
+        let registry = Registry::new();
+        let names: Vec<String> = strategies
+            .get_ref()
+            .join()
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        if let Some(name) = names.iter().find(|name| registry.get(name).is_none()) {
+            return error(
+                lineOf(text, strategies.span()),
+                format!("unknown strategy {}", name),
+            );
+        }
+        opts.Strategies = names;
+    }
+    opts.Seed = campaign.seed;
+    opts.Depth = campaign.depth;
+    if let Some(target) = &campaign.target {
+        opts.Target = expectWith(text, target, "target", TargetInfo::new)?;
+    }
+    for ty in campaign.types.iter().flatten() {
+        opts.Types
+            .push(expectWith(text, ty, "types", |ty| parse_types(ty)?.pop())?);
+    }
+
+    let output = campaign.output;
+    if let Some(path) = output.path {
+        opts.Output = dir.join(path);
+    }
+    opts.UBOutput = output.ub_path.map(|path| dir.join(path));
+    if let Some(format) = &output.format {
+        opts.Format = expectWith(text, format, "format", |f| match f {
+            "c" => Some(Format::C),
+            "smt" => Some(Format::Smt),
+            _ => None,
+        })?;
+    }
+    if let Some(parens) = &output.parens {
+        opts.Style = expectWith(text, parens, "parens", parse_parens)?;
+    }
+    if let Some(ub) = &output.ub {
+        opts.UB = expectWith(text, ub, "ub", parse_ub)?;
+    }
+    if let Some(infeasible) = &output.infeasible {
+        opts.SkipInfeasible = expectWith(text, infeasible, "infeasible", parse_infeasible)?;
+    }
+    if let Some(directives) = &output.directives {
+        opts.Directives = expectWith(text, directives, "directives", parse_directives)?;
+    }
+    opts.Force = output.force.unwrap_or(false);
+
+    for Symbol { name, ty } in &campaign.symbol {
+        let line = lineOf(text, name.span());
+        if !isIdentifier(name.get_ref()) {
+            return error(line, format!("invalid symbol name '{}'", name.get_ref()));
+        }
+        if opts.Symbols.iter().any(|sym| sym.name == *name.get_ref()) {
+            return error(line, format!("symbol {} declared twice", name.get_ref()));
+        }
+        if CDataTypes::getType(ty.get_ref()).is_none() {
+            return error(
+                lineOf(text, ty.span()),
+                format!("invalid type '{}'", ty.get_ref()),
+            );
+        }
+        opts.Symbols.push(Sym::new(name.get_ref(), ty.get_ref()));
+    }
+
+    for run in &campaign.run {
+        opts.Runs.push(
+            run.iter()
+                .map(|(key, val)| {
+                    let val = match val {
+                        Value::String(s) => s.clone(),
+                        val => val.to_string(),
+                    };
+                    (key.clone(), val)
+                })
+                .collect(),
+        );
+    }
+
+    Ok((ops, opts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symi::Draw::Draw;
+
+    #[test]
+    fn test_parse_campaign() {
+        let dir = Path::new("campaigns");
+        let text = include_str!("../campaigns/constant-folding.toml");
+        let (ops, opts) = parse_campaign(text, dir).unwrap();
+        assert_eq!(ops.len(), 6);
+        assert_eq!(opts.Target.to_string(), "x86_64-unknown-linux-gnu");
+        assert_eq!(opts.Seed, Some(42));
+        assert_eq!(opts.Depth, None);
+        assert_eq!(opts.Output, dir.join("constant-folding-gen.c"));
+        let symbols: Vec<String> = opts.Symbols.iter().map(|sym| sym.declare()).collect();
+        assert_eq!(
+            symbols,
+            ["int a", "int b", "unsigned int u", "signed char sc"]
+        );
+        assert_eq!(
+            opts.Runs,
+            [
+                vec![],
+                vec![(
+                    "support-symbolic-integer-casts".to_string(),
+                    "true".to_string()
+                )]
+            ]
+        );
+
+        let parse = |text: &str| {
+            parse_campaign(text, dir)
+                .map(|_| ())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(parse("seed = 1"), "a campaign needs ops");
+        assert_eq!(
+            parse("ops = \"<\"\n[output]\nformat = \"html\""),
+            "line 3: invalid value 'html' of format"
+        );
+        assert_eq!(
+            parse("ops = [\"<\",\n  \"<=>\"]"),
+            "line 1: invalid value '< <=>' of ops"
+        );
+        assert_eq!(
+            parse("ops = \"<\"\n[[symbol]]\nname = \"x\"\ntype = \"real\""),
+            "line 4: invalid type 'real'"
+        );
+        assert_eq!(
+            parse("ops = \"<\"\n[targets]\nx = 1"),
+            "line 2: unknown field `targets`, expected one of `ops`, `strategies`, `seed`, \
+             `depth`, `target`, `types`, `output`, `symbol`, `run`"
+        );
+        assert_eq!(
+            parse("ops = \"<\" # \"a\nseed"),
+            "line 2: expected `.`, `=`"
+        );
+    }
+}
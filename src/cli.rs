@@ -0,0 +1,316 @@
+//! Command line of auten.
+//!
+//! Without arguments auten runs the prompt. `auten generate` writes the tests
+//! picked by its flags and exits, with EXIT_USAGE if the command line is wrong
+//! and EXIT_FAILURE if the tests cannot be written, so that it can run
+//! unattended.
+
+use std::path::PathBuf;
+
+use symi::BinOp::Opcode;
+use symi::DataType::CDataTypes;
+use symi::Print::Parens;
+use symi::Strategy::Registry;
+use symi::Target::TargetInfo;
+use symi::TestFile::Directives;
+
+use crate::{Format, Options, UBMode};
+
+/// Exit code when the tests could not be generated or written.
+pub(crate) const EXIT_FAILURE: i32 = 1;
+/// Exit code of a malformed command line.
+pub(crate) const EXIT_USAGE: i32 = 2;
+
+pub(crate) const USAGE: &str = "\
+Usage: auten [repl]
+       auten generate --ops <ops> [options]
+       auten campaign <file> [-f]
+       auten help
+
+A campaign file describes the options of generate, see campaigns/.
+
+Options of generate:
+  --ops <ops>              Binary operators, e.g. '<,==' or '+ -'. 'cmp' stands
+                           for all comparisons and 'all' for every operator.
+  --types <types>          Comma separated C types of the symbols, e.g.
+                           'int,unsigned char' [default: all]
+  -o, --output <path>      File to write, '-' for stdout [default: fuzzed-tests.c]
+  -f, --force              Overwrite the output files if they exist
+  --seed <n>               Seed of the random and deep strategies
+  --depth <n>              Most nested operations in the expressions of the
+                           deep strategy [default: 3]
+  --strategy <names>       Comma separated strategies
+                           [default: boundary,mid-range,overlap,
+                           extremes,cast]
+  --target <triple>        Target the expectations hold for
+                           [default: x86_64-unknown-linux-gnu]
+  --format <c|smt>         A lit test, or SMT-LIB2 queries of each test [default: c]
+  --parens <all|minimal|warnings>
+                           Parentheses of the expressions [default: warnings]
+  --ub <skip|split|annotate>
+                           Tests with undefined behavior [default: skip]
+  --infeasible <skip|flag> Tests which cannot be reached [default: skip]
+  --run <key=value,...>    Add a RUN line with these analyzer-config options.
+                           May be repeated.
+  --directives <inline|above>
+                           Where -verify directives go [default: inline]";
+
+/// What the command line asks for.
+pub(crate) enum Command {
+    Repl,
+    Help,
+    Generate(Vec<Opcode>, Box<Options>),
+    /// A campaign file, and whether to overwrite its output files.
+    Campaign(PathBuf, bool),
+}
+
+/// Binary operators of the test generator, in C precedence order.
+const ALL_OPS: [Opcode; 18] = [
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::Rem,
+    Opcode::Add,
+    Opcode::Sub,
+    Opcode::Shl,
+    Opcode::Shr,
+    Opcode::LT,
+    Opcode::GT,
+    Opcode::LE,
+    Opcode::GE,
+    Opcode::EQ,
+    Opcode::NE,
+    Opcode::And,
+    Opcode::Xor,
+    Opcode::Or,
+    Opcode::LAnd,
+    Opcode::LOr,
+];
+
+/// Operators separated by commas or whitespace, None if any is unknown or
+/// there are none.
+pub(crate) fn parse_ops(text: &str) -> Option<Vec<Opcode>> {
+    let mut ops = vec![];
+    for op in text
+        .split([' ', '\t', '\n', ','])
+        .filter(|op| !op.is_empty())
+    {
+        match op {
+            "all" => ops.extend(ALL_OPS),
+            "cmp" => ops.extend(ALL_OPS.iter().filter(|op| op.isComparison())),
+            _ => ops.push(Opcode::getOpcode(op)?),
+        }
+    }
+    Some(ops).filter(|ops| !ops.is_empty())
+}
+
+/// Types separated by commas, or 'all'.
+pub(crate) fn parse_types(text: &str) -> Option<Vec<CDataTypes>> {
+    if text.trim() == "all" {
+        return Some(vec![]);
+    }
+    text.split(',')
+        .map(|ty| CDataTypes::getType(ty.trim()))
+        .collect()
+}
+
+/// analyzer-config options `key=value`, separated by commas or whitespace.
+pub(crate) fn parse_run(text: &str) -> Option<Vec<(String, String)>> {
+    text.split([' ', ','])
+        .filter(|config| !config.is_empty())
+        .map(|config| {
+            let (key, val) = config.split_once('=')?;
+            Some((key.to_string(), val.to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn parse_parens(text: &str) -> Option<Parens> {
+    match text.trim() {
+        "all" => Some(Parens::All),
+        "minimal" => Some(Parens::Minimal),
+        "warnings" => Some(Parens::Warnings),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_ub(text: &str) -> Option<UBMode> {
+    match text.trim() {
+        "skip" => Some(UBMode::Skip),
+        "split" => Some(UBMode::Split),
+        "annotate" => Some(UBMode::Annotate),
+        _ => None,
+    }
+}
+
+/// Whether to skip infeasible tests.
+pub(crate) fn parse_infeasible(text: &str) -> Option<bool> {
+    match text.trim() {
+        "skip" => Some(true),
+        "flag" => Some(false),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_directives(text: &str) -> Option<Directives> {
+    match text.trim() {
+        "inline" => Some(Directives::Inline),
+        "above" => Some(Directives::Above),
+        _ => None,
+    }
+}
+
+fn parse_format(text: &str) -> Option<Format> {
+    match text.trim() {
+        "c" => Some(Format::C),
+        "smt" => Some(Format::Smt),
+        _ => None,
+    }
+}
+
+/// Options of generate taking a value.
+const VALUE_FLAGS: [&str; 14] = [
+    "--ops",
+    "--types",
+    "-o",
+    "--output",
+    "--seed",
+    "--depth",
+    "--strategy",
+    "--target",
+    "--format",
+    "--parens",
+    "--ub",
+    "--infeasible",
+    "--run",
+    "--directives",
+];
+
+/// Parse the arguments following the program name.
+pub(crate) fn parse_args(args: &[String]) -> Result<Command, String> {
+    let (command, flags) = match args.split_first() {
+        None => return Ok(Command::Repl),
+        Some((command, flags)) => (command.as_str(), flags),
+    };
+    match command {
+        "repl" if flags.is_empty() => return Ok(Command::Repl),
+        "help" | "-h" | "--help" => return Ok(Command::Help),
+        "campaign" => {
+            return match flags {
+                [file] => Ok(Command::Campaign(PathBuf::from(file), false)),
+                [file, force] | [force, file] if force == "-f" || force == "--force" => {
+                    Ok(Command::Campaign(PathBuf::from(file), true))
+                }
+                _ => Err("campaign needs a file".to_string()),
+            }
+        }
+        "generate" => {}
+        _ => return Err(format!("unknown command '{}'", args.join(" "))),
+    }
+
+    let mut ops = None;
+    let mut opts = Options::default();
+    let mut it = flags.iter();
+    while let Some(arg) = it.next() {
+        // Values follow their flag, or an '=' in it.
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, val)) if flag.starts_with("--") => (flag, Some(val.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        if let "-f" | "--force" = flag {
+            opts.Force = true;
+            continue;
+        }
+        if !VALUE_FLAGS.contains(&flag) {
+            return Err(format!("unknown option {}", flag));
+        }
+        let val = match inline.or_else(|| it.next().cloned()) {
+            Some(val) => val,
+            None => return Err(format!("missing value of {}", flag)),
+        };
+        let invalid = || format!("invalid value '{}' of {}", val, flag);
+
+        match flag {
+            "--ops" => ops = Some(parse_ops(&val).ok_or_else(invalid)?),
+            "--types" => opts.Types = parse_types(&val).ok_or_else(invalid)?,
+            "-o" | "--output" => opts.Output = PathBuf::from(&val),
+            "--seed" => opts.Seed = Some(val.parse().map_err(|_| invalid())?),
+            "--depth" => opts.Depth = Some(val.parse().map_err(|_| invalid())?),
+            "--strategy" => {
+                let registry = Registry::new();
+                let names: Vec<String> = val.split(',').map(|n| n.trim().to_string()).collect();
+                if names.iter().any(|name| registry.get(name).is_none()) {
+                    return Err(format!(
+                        "{}, available: {}",
+                        invalid(),
+                        registry.getNames().join(", ")
+                    ));
+                }
+                opts.Strategies = names;
+            }
+            "--target" => opts.Target = TargetInfo::new(&val).ok_or_else(invalid)?,
+            "--format" => opts.Format = parse_format(&val).ok_or_else(invalid)?,
+            "--parens" => opts.Style = parse_parens(&val).ok_or_else(invalid)?,
+            "--ub" => opts.UB = parse_ub(&val).ok_or_else(invalid)?,
+            "--infeasible" => opts.SkipInfeasible = parse_infeasible(&val).ok_or_else(invalid)?,
+            "--run" => opts.Runs.push(parse_run(&val).ok_or_else(invalid)?),
+            "--directives" => opts.Directives = parse_directives(&val).ok_or_else(invalid)?,
+            _ => unreachable!(),
+        }
+    }
+
+    match ops {
+        Some(ops) => Ok(Command::Generate(ops, Box::new(opts))),
+        None => Err("generate needs --ops".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert!(matches!(parse_args(&[]), Ok(Command::Repl)));
+        assert!(matches!(parse_args(&args("help")), Ok(Command::Help)));
+        assert!(matches!(
+            parse_args(&args("campaign -f c.toml")),
+            Ok(Command::Campaign(file, true)) if file == Path::new("c.toml")
+        ));
+
+        let Ok(Command::Generate(ops, opts)) = parse_args(&args(
+            "generate --ops cmp,+ --types=int,short -o out.c -f --seed 7 --depth 5 \
+             --target i686-pc-linux-gnu --run a=1,b=2 --run c=3 --ub split",
+        )) else {
+            panic!("generate not parsed");
+        };
+        assert_eq!(ops.len(), 7);
+        assert_eq!(opts.Types, [CDataTypes::Int, CDataTypes::Short]);
+        assert_eq!(opts.Output, PathBuf::from("out.c"));
+        assert!(opts.Force);
+        assert_eq!(opts.Seed, Some(7));
+        assert_eq!(opts.Depth, Some(5));
+        assert_eq!(opts.Target.to_string(), "i686-pc-linux-gnu");
+        assert_eq!(opts.Runs.len(), 2);
+        assert!(opts.UB == UBMode::Split);
+
+        for (line, err) in [
+            ("generate", "generate needs --ops"),
+            ("generate --ops", "missing value of --ops"),
+            ("generate --ops <=> ", "invalid value '<=>' of --ops"),
+            (
+                "generate --ops + --types real",
+                "invalid value 'real' of --types",
+            ),
+            ("generate --ops + --fast", "unknown option --fast"),
+            ("frobnicate", "unknown command 'frobnicate'"),
+            ("campaign a.toml b.toml", "campaign needs a file"),
+        ] {
+            assert_eq!(parse_args(&args(line)).err().as_deref(), Some(err));
+        }
+    }
+}
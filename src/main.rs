@@ -11,36 +11,116 @@
 //!
 #![allow(non_snake_case, dead_code, unused_variables, unused_imports)]
 use std::fs::File;
-use std::io::{prelude::*, stdin, stdout, Error};
+use std::io::{prelude::*, stdin, stdout, Error, ErrorKind};
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::sync::Arc;
 
 use symi::BinOp::Opcode;
+use symi::Corner::CornerTest;
 use symi::DataType::CDataTypes;
 use symi::Draw::Draw;
-use symi::Expr::{BinarySymExpr, ChainedBSE};
+use symi::Expr::BinarySymExpr;
+use symi::Oracle::Feasibility;
+use symi::Print::Parens;
+use symi::Strategy::{merge, CornerCaseStrategy, Deep, Random, Registry};
 use symi::Symbol::{Conc, Sym};
+use symi::Target::TargetInfo;
 use symi::Test::Test;
+use symi::TestFile::{Directives, TestFile, TestFunction};
 
+mod campaign;
+mod cli;
 mod utils;
+use campaign::parse_campaign;
+use cli::*;
 use utils::*;
 
+/// What to do with tests whose behavior may be undefined, see
+/// symi::Undefined.
+#[derive(Copy, Clone, PartialEq)]
+enum UBMode {
+    /// Leave them out.
+    Skip,
+    /// Write them to a file of their own, without any expectations.
+    Split,
+    /// Expect the warning of the checker reporting the UB, and leave out the
+    /// tests where that is not possible.
+    Annotate,
+}
+
+/// What is written for each test.
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    /// A lit test of C, see symi::TestFile.
+    C,
+    /// SMT-LIB2 queries of the expectations, see symi::Smt.
+    Smt,
+}
+
+/// How tests are generated and written, as picked in the prompt or on the
+/// command line.
+struct Options {
+    /// Names of the strategies in the Registry.
+    Strategies: Vec<String>,
+    /// Seed of the random and deep strategies, their own if None.
+    Seed: Option<u64>,
+    /// Depth of the expressions of the deep strategy, its own if None.
+    Depth: Option<usize>,
+    /// Symbols to pair up, those of set_of_syms if empty.
+    Symbols: Vec<Arc<Sym>>,
+    /// Types of the symbols, all types if empty.
+    Types: Vec<CDataTypes>,
+    Target: TargetInfo,
+    SkipInfeasible: bool,
+    Style: Parens,
+    UB: UBMode,
+    /// analyzer-config options of each RUN line, see symi::TestFile.
+    Runs: Vec<Vec<(String, String)>>,
+    Directives: Directives,
+    Format: Format,
+    /// File the tests are written to, stdout if "-".
+    Output: PathBuf,
+    /// File tests with undefined behavior are split to, next to Output if
+    /// None.
+    UBOutput: Option<PathBuf>,
+    /// Whether files which exist are overwritten.
+    Force: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            Strategies: ["boundary", "mid-range", "overlap", "extremes", "cast"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            Seed: None,
+            Depth: None,
+            Symbols: vec![],
+            Types: vec![],
+            Target: TargetInfo::default(),
+            SkipInfeasible: true,
+            Style: Parens::Warnings,
+            UB: UBMode::Skip,
+            Runs: vec![],
+            Directives: Directives::Inline,
+            Format: Format::C,
+            Output: PathBuf::from("fuzzed-tests.c"),
+            UBOutput: None,
+            Force: false,
+        }
+    }
+}
+
 /// Generate symbols on the run and add them to file.
-fn dump_testRemainedRules(test_file: &mut File) {
+fn dump_testRemainedRules(test_file: &mut TestFile, target: &TargetInfo) {
     let a = Sym::new("a", "unsigned int");
     let b = Sym::new("b", "unsigned int");
     let c = Sym::new("c", "int");
     let d = Sym::new("d", "int");
     let conc30 = Conc::new(30, CDataTypes::Int);
     let conc50 = Conc::new(50, CDataTypes::Int);
-    let fn_name = "bar";
-
-    let sub: Vec<Arc<Sym>> = [&a, &b, &c, &d]
-        .iter()
-        .map(|sym| Arc::clone(&sym))
-        .collect();
-
-    test_header(test_file, fn_name, &sub);
 
     let cond1 = BinarySymExpr::new(&a, &conc30, Opcode::LE);
     let cond2 = BinarySymExpr::new(&b, &conc50, Opcode::LE);
@@ -54,26 +134,29 @@ fn dump_testRemainedRules(test_file: &mut File) {
     #[cfg(debug_assertions)]
     println!("BSE = tmp\n{}", tmp.dump(None));
 
-    let T1 = Test::new(&cond, &amodbl50);
-    test_file.write_fmt(format_args!("{}", T1)).unwrap();
+    let T1 = Test::new(&cond, &amodbl50, target);
+    test_file.addFunction(
+        TestFunction::new("bar")
+            .returns("int")
+            .param(&a)
+            .param(&b)
+            .param(&c)
+            .param(&d)
+            .statement(T1.to_string()),
+    );
 }
 
 /// This will generate all corner cases corresponding to S1 and S2. These two
 /// symbols may be of different types. It should not matter in the construction
 /// of test cases. All we need to do is construct corner cases associated to
-/// these two symbols. Also, put assert expressions in the file.
-fn dump_all_corner_tests(test_file: &mut File, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode) {
-    let BSE = BinarySymExpr::new(S1, S2, Op);
-    // We have the BSE, an expression for which we need to generate test cases.
-    // That is, we first need to construct constraints using < family of
-    // operators.
-    let BSE2 = BinarySymExpr::new(S1, S2, Opcode::LE);
-    let BSE3 = BinarySymExpr::new(&BSE, &BSE2, Opcode::LAnd);
-    let BSE4 = BinarySymExpr::new(&BSE, &BSE2, Opcode::LOr);
-    let BSE5 = BinarySymExpr::new(&BSE3, &BSE4, Opcode::Xor);
-
-    // println!("{}", BSE5.dump(None));
-
+/// these two symbols.
+fn all_corner_tests(
+    S1: &Arc<Sym>,
+    S2: &Arc<Sym>,
+    Ops: &[Opcode],
+    Strategies: &[&dyn CornerCaseStrategy],
+    Opts: &Options,
+) -> Vec<Arc<CornerTest>> {
     // Logic
     // -----
     // We have two symbols of types t1 and t2, so we can figure out the limits
@@ -84,119 +167,409 @@ fn dump_all_corner_tests(test_file: &mut File, S1: &Arc<Sym>, S2: &Arc<Sym>, Op:
     // t1 ∈ [INT_MIN, INT_MAX]
     // t2 ∈ [0, UINT_MAX]
 
-    if S1.isa(S2.getType()) {
-        // All possible test cases
-        // <---|------------------------|--------------------|--->
-        //    T_MIN                   T_MID                T_MAX
-        //
-        // For (T_MIN, T_MAX):
-        //   1. Non overlapping        : [1,2] and [3,4]
-        //   2. Partially overlapping  : [1,3] and [2,4]
-        //   3. Completely overlapping : [1,3] and [1,3]
-        // For overflows  : ...same three cases
-        // For underflows : ...same three cases
-        let (T_MIN, T_MAX) = S1.getTypeRange();
-        let T_MID = (T_MIN >> 1) + (T_MAX >> 1) + (((T_MIN & 1) + (T_MAX & 1)) >> 1);
-
-        let BSE = Sym::getConstraintsAround(S1, T_MID, 3);
-        println!("BSE = {}", BSE);
-
-        let (T_MID_LL, T_MID_LR) = (
-            Conc::new(T_MID - 3, S1.getType()),
-            Conc::new(T_MID - 1, S1.getType()),
-        );
-        let (T_MID_RL, T_MID_RR) = (
-            Conc::new(T_MID + 1, S1.getType()),
-            Conc::new(T_MID + 3, S1.getType()),
-        );
+    // All possible test cases
+    // <---|------------------------|--------------------|--->
+    //    T_MIN                   T_MID                T_MAX
+    //
+    // For (T_MIN, T_MAX):
+    //   1. Non overlapping        : [1,2] and [3,4]
+    //   2. Partially overlapping  : [1,3] and [2,4]
+    //   3. Completely overlapping : [1,3] and [1,3]
+    // For overflows  : ...same three cases
+    // For underflows : ...same three cases
+    //
+    // each around T_MIN, T_MID and T_MAX, see Corner::cases. How the cases are
+    // picked is up to the strategies, see symi::Strategy. The tests of all
+    // operators for the same windows are checked under one conditional.
+    //
+    // `%`, shifts and bitwise operators take no floating operands.
+    let Floating = !S1.ty.isInteger() || !S2.ty.isInteger();
+    let Ops: Vec<Opcode> = Ops
+        .iter()
+        .copied()
+        .filter(|Op| !(Floating && Op.needsIntegers()))
+        .collect();
+    let Tests = merge(Strategies.iter().flat_map(|Strategy| {
+        Ops.iter()
+            .flat_map(move |Op| Strategy.generate(S1, S2, *Op, &Opts.Target))
+    }));
 
-        let C1 = BinarySymExpr::new(S1, &T_MID_LL, Opcode::GE);
-        let C2 = BinarySymExpr::new(S1, &T_MID_LR, Opcode::LE);
-        let C3 = BinarySymExpr::new(S2, &T_MID_RL, Opcode::GE);
-        let C4 = BinarySymExpr::new(S2, &T_MID_RR, Opcode::LE);
+    // let test = Test::new(&BSE3, &BSE);
+    // test_file.addTest(&test, test.to_string());
+
+    // The body of an infeasible test is dead code and checks nothing. Unless
+    // skipped, it is kept with a check that it is not reached. Tests whose
+    // assertions the oracle could not decide have nothing left to check, and
+    // neither have those whose assertions the oracle never reaches although
+    // their body is reachable.
+    Tests
+        .into_iter()
+        .filter(|ThisTest| {
+            !Opts.SkipInfeasible || ThisTest.getFeasibility() != Feasibility::Infeasible
+        })
+        .filter(|ThisTest| !ThisTest.getAssertions().is_empty())
+        .filter(|ThisTest| ThisTest.isConsistent())
+        .collect()
+}
 
-        let T_MID_L = Conc::new(T_MID - 3, S1.getType());
-        let T_MID_R = Conc::new(T_MID + 3, S1.getType());
+/// Put the tests in the file, and deal with those whose behavior may be
+/// undefined as Opts.UB says.
+fn dump_all_corner_tests(
+    test_file: &mut TestFile,
+    ub_file: &mut Option<TestFile>,
+    Tests: &[Arc<CornerTest>],
+    Opts: &Options,
+) {
+    for ThisTest in Tests {
+        // The analyzer may do anything once the behavior is undefined. Only
+        // the assertions which may be undefined are set apart, the others
+        // are checked all the same.
+        let (Safe, Hazardous) = ThisTest.partitionHazards();
+        if let Some(Safe) = Safe {
+            test_file.addTest(&Safe, Safe.print(Opts.Style));
+        }
+        let Some(ThisTest) = Hazardous else {
+            continue;
+        };
+        let Hazards = ThisTest.getHazards();
+        match (Opts.UB, ub_file.as_mut()) {
+            (UBMode::Split, Some(ub_file)) => {
+                let Notes: String = Hazards
+                    .iter()
+                    .map(|Hazard| format!("\n  // UB: {}", Hazard))
+                    .collect();
+                ub_file.addTest(&ThisTest, Notes + &ThisTest.print(Opts.Style));
+            }
+            (UBMode::Annotate, _) => {
+                if let Some(Annotated) = ThisTest.printUB(Opts.Style) {
+                    test_file.addTest(&ThisTest, Annotated);
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
-        let LHS = BinarySymExpr::new(S1, &T_MID_L, Opcode::LE);
-        let RHS = BinarySymExpr::new(S2, &T_MID_R, Opcode::GE);
+/// Append SMT-LIB2 queries of each test to `script`, see Test::toSmt. Tests
+/// involving other types than integers have none.
+fn dump_smt_queries(script: &mut String, Tests: &[Arc<CornerTest>]) {
+    for ThisTest in Tests {
+        if let Some(Queries) = ThisTest.toSmt() {
+            // Each test declares its symbols anew.
+            if !script.is_empty() {
+                *script += "(reset)\n";
+            }
+            *script += &Queries;
+        }
+    }
+}
 
-        // let ThisConditional = ChainedBSE::new(&[&C1, &C2, &C3, &C4], Op).join();
+/// Write `text` to `path`, or to stdout if it is "-".
+fn write_output(path: &Path, text: &str) -> Result<(), Error> {
+    if path == Path::new("-") {
+        return stdout().write_all(text.as_bytes());
+    }
+    File::create(path)?.write_all(text.as_bytes())
+}
 
-        let ThisConditional = BinarySymExpr::new(&LHS, &RHS, Opcode::LAnd);
-        ThisConditional.iter();
+/// The file split tests with undefined behavior go to, `<output>-ub.<ext>`.
+fn ub_output(path: &Path) -> PathBuf {
+    if path == Path::new("-") {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{}-ub.{}", stem, ext.to_string_lossy())),
+        None => path.with_file_name(format!("{}-ub", stem)),
+    }
+}
 
-        let ThisAssert = BinarySymExpr::new(S1, S2, Op);
-        let ThisTest = Test::new(&ThisConditional, &ThisAssert);
-        test_file.write_fmt(format_args!("{}", ThisTest)).unwrap();
-    } else if S1.getType() < S2.getType() {
-        #[cfg(debug_assertions)]
-        println!("{} < {}", S1.declare(), S2.declare());
-    } else {
-        #[cfg(debug_assertions)]
-        println!("{} > {}", S1.declare(), S2.declare());
+/// The files fuzz writes to, stdout left out.
+fn outputs(Opts: &Options) -> Vec<PathBuf> {
+    let mut Paths = vec![Opts.Output.clone()];
+    if Opts.Format == Format::C && Opts.UB == UBMode::Split {
+        Paths.push(
+            Opts.UBOutput
+                .clone()
+                .unwrap_or_else(|| ub_output(&Opts.Output)),
+        );
     }
+    Paths.retain(|path| path != Path::new("-"));
+    Paths
+}
 
-    // let test = Test::new(&BSE3, &BSE);
-    // test_file.write_fmt(format_args!("{}", test)).unwrap();
+/// The files fuzz would overwrite, none if forced to.
+fn existing_outputs(Opts: &Options) -> Vec<PathBuf> {
+    if Opts.Force {
+        return vec![];
+    }
+    outputs(Opts)
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
 }
 
 /// Main driver for constructing symbols and their associated constraints and
 /// build test cases, put them in a .c file.
-fn fuzz(Op: Opcode) -> Result<(), Error> {
-    // TODO: Rename files if already exists.
-    let mut test = File::create("fuzzed-tests.c").unwrap();
+fn fuzz(Ops: &[Opcode], Opts: &Options) -> Result<(), Error> {
+    // Nothing is written unless all of the files can be.
+    if let Some(path) = existing_outputs(Opts).first() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} exists, use --force to overwrite it", path.display()),
+        ));
+    }
+
+    let mut Strategies = Registry::new();
+    if let Some(seed) = Opts.Seed {
+        Strategies.register(Box::new(Random {
+            seed,
+            ..Random::default()
+        }));
+    }
+    let Defaults = Deep::default();
+    Strategies.register(Box::new(Deep {
+        seed: Opts.Seed.unwrap_or(Defaults.seed),
+        depth: Opts.Depth.unwrap_or(Defaults.depth),
+        ..Defaults
+    }));
+    let Strategies: Vec<&dyn CornerCaseStrategy> = Opts
+        .Strategies
+        .iter()
+        .filter_map(|name| Strategies.get(name))
+        .collect();
+
+    let mut smt = String::new();
+    let mut test = TestFile::new("foo", &Opts.Target);
+    let mut ub = match Opts.UB {
+        UBMode::Split => Some(TestFile::new("foo_ub", &Opts.Target)),
+        _ => None,
+    };
+    for file in std::iter::once(&mut test).chain(ub.as_mut()) {
+        for Run in &Opts.Runs {
+            let Configs: Vec<(&str, &str)> = Run
+                .iter()
+                .map(|(key, val)| (key.as_str(), val.as_str()))
+                .collect();
+            file.addRun(&Configs);
+        }
+        file.setDirectives(Opts.Directives);
+    }
+    // The results of tests with undefined behavior are unspecified, nothing
+    // can be expected of them.
+    if let Some(ub) = ub.as_mut() {
+        ub.setDirectives(Directives::Omitted);
+    }
 
-    // let mut test2 = File::create("testRemainedRules.c").unwrap();
-    // dump_testRemainedRules(&mut test2);
-    // test_footer(&mut test2);
+    // let mut test2 = TestFile::new("bar", &Opts.Target);
+    // dump_testRemainedRules(&mut test2, &Opts.Target);
+    // write!(File::create("testRemainedRules.c")?, "{}", test2)?;
     // println!("Fuzzed tests in testRemainedRules.c");
 
     // Set of symbols for fuzzing. Every data type in C will declare two symbols
-    // namely, *1 and *2 (* denoting the data type's initials).
-    let (Symset, AvailableTypes) = set_of_syms();
-
-    test_header(&mut test, "foo", &Symset[..]);
+    // namely, *1 and *2 (* denoting the data type's initials), unless the
+    // symbols are given.
+    let (Symset, mut AvailableTypes) = if Opts.Symbols.is_empty() {
+        set_of_syms()
+    } else {
+        types_of_syms(&Opts.Symbols)
+    };
+    if !Opts.Types.is_empty() {
+        AvailableTypes.retain(|ty| Opts.Types.contains(ty));
+    }
+    let mut Pairs = vec![];
 
-    // Combine each pair with same type.
+    // Combine each pair with same type. A type may have a single symbol.
     for ty in AvailableTypes.iter() {
-        let (S1, S2) = search_pair_of_types(&Symset, *ty, *ty).unwrap();
-        dump_all_corner_tests(&mut test, &S1, &S2, Op.clone());
+        Pairs.extend(search_pair_of_types(&Symset, *ty, *ty));
     }
 
     // Handle for pairs in moving order. First type with rest types, second type
     // with rest types except first, and so on.
     for ty1_id in 0..AvailableTypes.len() {
         for ty2_id in ty1_id + 1..AvailableTypes.len() {
-            let (S1, S2) =
-                search_pair_of_types(&Symset, AvailableTypes[ty1_id], AvailableTypes[ty2_id])
-                    .unwrap();
-
-            dump_all_corner_tests(&mut test, &S1, &S2, Op);
+            Pairs.extend(search_pair_of_types(
+                &Symset,
+                AvailableTypes[ty1_id],
+                AvailableTypes[ty2_id],
+            ));
         }
     }
 
-    test_footer(&mut test);
+    for (S1, S2) in Pairs {
+        let Tests = all_corner_tests(&S1, &S2, Ops, &Strategies, Opts);
+        match Opts.Format {
+            Format::C => dump_all_corner_tests(&mut test, &mut ub, &Tests, Opts),
+            Format::Smt => dump_smt_queries(&mut smt, &Tests),
+        }
+    }
 
+    if Opts.Format == Format::Smt {
+        return write_output(&Opts.Output, &smt);
+    }
+    write_output(&Opts.Output, &test.to_string())?;
+    if let Some(ub) = ub {
+        let Path = Opts
+            .UBOutput
+            .clone()
+            .unwrap_or_else(|| ub_output(&Opts.Output));
+        write_output(&Path, &ub.to_string())?;
+    }
     Ok(())
 }
 
-fn main() {
+/// Read commands and operators from stdin until 'quit', and write the tests
+/// of each line of operators to fuzzed-tests.c. Files which exist are only
+/// overwritten once confirmed.
+fn repl() {
+    let Strategies = Registry::new();
+    let mut Opts = Options::default();
+
     let mut input = String::with_capacity(5);
     loop {
         input.clear();
-        print!("> Enter binary operator for which to generate tests\n> ");
+        print!(
+            "> Enter binary operators for which to generate tests (target: {})\n> ",
+            Opts.Target
+        );
         let _ = stdout().flush();
-        stdin().read_line(&mut input).expect("IO error");
+        // Stop at the end of the input as well.
+        if stdin().read_line(&mut input).expect("IO error") == 0 {
+            exit(0);
+        }
 
         if "q\n" == input || "quit\n" == input {
             exit(0);
         }
 
-        if let Some(opcode) = Opcode::getOpcode(input.as_str().strip_suffix('\n').unwrap()) {
-            match fuzz(opcode) {
-                Ok(_) => println!("Fuzzed tests for {} operator.", opcode),
-                Err(_) => eprintln!("Failed to fuzz tests. Aborted!"),
+        // Switch the target with 'target <triple>'.
+        if let Some(triple) = input.trim_end().strip_prefix("target ") {
+            match TargetInfo::new(triple.trim()) {
+                Some(target) => {
+                    println!("Generating tests for {}.", target);
+                    Opts.Target = target;
+                }
+                None => println!("Unknown target {}!", triple.trim()),
+            }
+            continue;
+        }
+
+        // Pick the strategies with 'strategy <name>...'.
+        if let Some(names) = input.trim_end().strip_prefix("strategy") {
+            let names: Vec<&str> = names.split([' ', ',']).filter(|n| !n.is_empty()).collect();
+            match names.iter().find(|name| Strategies.get(name).is_none()) {
+                Some(name) => println!(
+                    "Unknown strategy {}! Available: {}",
+                    name,
+                    Strategies.getNames().join(", ")
+                ),
+                None if names.is_empty() => println!("Using {}.", Opts.Strategies.join(", ")),
+                None => {
+                    Opts.Strategies = Strategies
+                        .getNames()
+                        .into_iter()
+                        .filter(|name| names.contains(name))
+                        .map(|name| name.to_string())
+                        .collect();
+                    println!("Using {}.", Opts.Strategies.join(", "));
+                }
+            }
+            continue;
+        }
+
+        // Seed the random and deep strategies with 'seed <n>'.
+        if let Some(seed) = input.trim_end().strip_prefix("seed") {
+            match seed.trim().parse() {
+                Ok(seed) => Opts.Seed = Some(seed),
+                Err(_) => println!("Usage: seed <n>"),
+            }
+            continue;
+        }
+
+        // Bound the expressions of the deep strategy with 'depth <n>'.
+        if let Some(depth) = input.trim_end().strip_prefix("depth") {
+            match depth.trim().parse() {
+                Ok(depth) => Opts.Depth = Some(depth),
+                Err(_) => println!("Usage: depth <n>"),
+            }
+            continue;
+        }
+
+        // Skip or keep tests which can never be reached with
+        // 'infeasible skip|flag'.
+        if let Some(mode) = input.trim_end().strip_prefix("infeasible") {
+            match parse_infeasible(mode) {
+                Some(skip) => Opts.SkipInfeasible = skip,
+                None => println!("Usage: infeasible skip|flag"),
+            }
+            continue;
+        }
+
+        // How much to parenthesize with 'parens all|minimal|warnings'.
+        if let Some(style) = input.trim_end().strip_prefix("parens") {
+            match parse_parens(style) {
+                Some(style) => Opts.Style = style,
+                None => println!("Usage: parens all|minimal|warnings"),
+            }
+            continue;
+        }
+
+        // Tests with undefined behavior with 'ub skip|split|annotate'.
+        if let Some(mode) = input.trim_end().strip_prefix("ub") {
+            match parse_ub(mode) {
+                Some(mode) => Opts.UB = mode,
+                None => println!("Usage: ub skip|split|annotate"),
+            }
+            continue;
+        }
+
+        // Add a RUN line with 'run <key>=<value>...', drop them all with
+        // 'run reset'.
+        if let Some(configs) = input.trim_end().strip_prefix("run") {
+            if configs.trim() == "reset" {
+                Opts.Runs.clear();
+                continue;
+            }
+            match parse_run(configs) {
+                Some(Run) => Opts.Runs.push(Run),
+                None => println!("Usage: run [<key>=<value>...] | run reset"),
+            }
+            continue;
+        }
+
+        // Where -verify directives go with 'directives inline|above'.
+        if let Some(place) = input.trim_end().strip_prefix("directives") {
+            match parse_directives(place) {
+                Some(place) => Opts.Directives = place,
+                None => println!("Usage: directives inline|above"),
+            }
+            continue;
+        }
+
+        // One or more operators, 'cmp' standing for all comparisons and 'all'
+        // for every operator.
+        if let Some(Ops) = parse_ops(&input) {
+            let Names: Vec<String> = Ops.iter().map(|op| op.to_string()).collect();
+            let Existing = existing_outputs(&Opts);
+            if !Existing.is_empty() {
+                let Paths: Vec<String> = Existing.iter().map(|p| p.display().to_string()).collect();
+                print!("> {} exists, overwrite it? [y/N] ", Paths.join(" and "));
+                let _ = stdout().flush();
+                let mut answer = String::new();
+                if stdin().read_line(&mut answer).expect("IO error") == 0 {
+                    exit(0);
+                }
+                if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                    println!("Left {} alone.", Paths.join(" and "));
+                    continue;
+                }
+            }
+            Opts.Force = !Existing.is_empty();
+            let result = fuzz(&Ops, &Opts);
+            Opts.Force = false;
+            match result {
+                Ok(_) => println!("Fuzzed tests for {} operator.", Names.join(" ")),
+                Err(err) => eprintln!("Failed to fuzz tests: {}. Aborted!", err),
             }
         } else {
             println!("Incorrect opcode! 'quit' to exit.");
@@ -204,6 +577,46 @@ fn main() {
     }
 }
 
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match parse_args(&args) {
+        Ok(Command::Repl) => repl(),
+        Ok(Command::Help) => println!("{}", USAGE),
+        Ok(Command::Campaign(File, Force)) => {
+            let Text = match std::fs::read_to_string(&File) {
+                Ok(Text) => Text,
+                Err(err) => {
+                    eprintln!("auten: {}: {}", File.display(), err);
+                    exit(EXIT_FAILURE);
+                }
+            };
+            let Dir = File.parent().unwrap_or(Path::new(""));
+            let (Ops, mut Opts) = match parse_campaign(&Text, Dir) {
+                Ok(Campaign) => Campaign,
+                Err(err) => {
+                    eprintln!("auten: {}: {}", File.display(), err);
+                    exit(EXIT_USAGE);
+                }
+            };
+            Opts.Force |= Force;
+            if let Err(err) = fuzz(&Ops, &Opts) {
+                eprintln!("auten: {}", err);
+                exit(EXIT_FAILURE);
+            }
+        }
+        Ok(Command::Generate(Ops, Opts)) => {
+            if let Err(err) = fuzz(&Ops, &Opts) {
+                eprintln!("auten: {}", err);
+                exit(EXIT_FAILURE);
+            }
+        }
+        Err(err) => {
+            eprintln!("auten: {}\n\n{}", err, USAGE);
+            exit(EXIT_USAGE);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -1,5 +1,3 @@
-use std::fs::File;
-use std::io::prelude::*;
 use std::sync::Arc;
 
 use symi::BinOp::Opcode;
@@ -16,9 +14,10 @@ pub(crate) fn set_of_syms() -> (Vec<Arc<Sym>>, Vec<CDataTypes>) {
     let mut set: Vec<Arc<Sym>> = vec![];
     let mut ty: Vec<CDataTypes> = vec![];
 
-    // set.push(Sym::new("ch1", "char"));
-    // set.push(Sym::new("ch2", "char"));
-    // ty.push(CDataTypes::Char);
+    // Signedness of plain char follows the target tests are generated for.
+    set.push(Sym::new("ch1", "char"));
+    set.push(Sym::new("ch2", "char"));
+    ty.push(CDataTypes::Char);
 
     set.push(Sym::new("sch1", "signed char"));
     set.push(Sym::new("sch2", "signed char"));
@@ -44,13 +43,53 @@ pub(crate) fn set_of_syms() -> (Vec<Arc<Sym>>, Vec<CDataTypes>) {
     set.push(Sym::new("s2", "signed int"));
     ty.push(CDataTypes::Int);
 
+    set.push(Sym::new("l1", "long"));
+    set.push(Sym::new("l2", "long"));
+    ty.push(CDataTypes::Long);
+
+    set.push(Sym::new("ul1", "unsigned long"));
+    set.push(Sym::new("ul2", "unsigned long"));
+    ty.push(CDataTypes::UnsignedLong);
+
+    set.push(Sym::new("ll1", "long long"));
+    set.push(Sym::new("ll2", "long long"));
+    ty.push(CDataTypes::LongLong);
+
+    set.push(Sym::new("ull1", "unsigned long long"));
+    set.push(Sym::new("ull2", "unsigned long long"));
+    ty.push(CDataTypes::UnsignedLongLong);
+
+    set.push(Sym::new("f1", "float"));
+    set.push(Sym::new("f2", "float"));
+    ty.push(CDataTypes::Float);
+
+    set.push(Sym::new("d1", "double"));
+    set.push(Sym::new("d2", "double"));
+    ty.push(CDataTypes::Double);
+
+    // Format of long double follows the target, see CDataTypes::getRange().
+    set.push(Sym::new("ld1", "long double"));
+    set.push(Sym::new("ld2", "long double"));
+    ty.push(CDataTypes::LongDouble);
+
     (set, ty)
 }
 
+/// Types of `syms` in order of appearance, with the symbols themselves.
+pub(crate) fn types_of_syms(syms: &[Arc<Sym>]) -> (Vec<Arc<Sym>>, Vec<CDataTypes>) {
+    let mut ty: Vec<CDataTypes> = vec![];
+    for sym in syms {
+        if !ty.contains(&sym.ty) {
+            ty.push(sym.ty);
+        }
+    }
+    (syms.to_vec(), ty)
+}
+
 /// Given a vector of symbols, find first pair of symbols for certain given
 /// types.
-pub(crate) fn search_pair_of_types<'a>(
-    Symset: &'a Vec<Arc<Sym>>,
+pub(crate) fn search_pair_of_types(
+    Symset: &[Arc<Sym>],
     ty1: CDataTypes,
     ty2: CDataTypes,
 ) -> Option<(Arc<Sym>, Arc<Sym>)> {
@@ -66,55 +105,3 @@ pub(crate) fn search_pair_of_types<'a>(
 
     None
 }
-
-/// Main routine to dump headers for test file.
-pub(crate) fn test_header(test_file: &mut File, fn_name: &str, syms: &[Arc<Sym>]) {
-    test_file
-        .write_fmt(format_args!(
-            "
-void clang_analyzer_eval(int);
-
-int {}(",
-            fn_name
-        ))
-        .unwrap();
-
-    let mut it = syms.iter().peekable();
-    while let Some(sym) = it.next() {
-        // TODO: Optimize this! This condition is true for last element only.
-        // Check if peek() is alread optimized.
-        if it.peek().is_none() {
-            test_file
-                .write_fmt(format_args!("{}", sym.declare()))
-                .unwrap();
-            continue;
-        }
-        test_file
-            .write_fmt(format_args!("\n\t{}, ", sym.declare()))
-            .unwrap();
-    }
-
-    test_file.write_fmt(format_args!(") {{")).unwrap();
-}
-
-// TODO: We need more flexibility while inserting headers and functions.
-// 1. We need variable-length arguments in functions. Eg:
-//      int foo(int a, int b) // <- Custom declarations, instead of fixing 7x8
-//
-// 2. More asserts should be allowable inside one conditional. For this, will we
-//    have to separate Conditional and Assertion in Test? Eg:
-//      if () { clang_analyzer_eval(assert1); clang_analyzer_eval(assert2); }
-//
-// 3. Constructing symbol declarations at any point will be helpful.
-/// Dump function header and return values (if any) for the to-be tested
-/// function.
-// TODO : Redundant, replace with fn_header
-pub(crate) fn test_footer(test_file: &mut File) {
-    test_file
-        .write_fmt(format_args!(
-            "
-  return 0;
-}}"
-        ))
-        .unwrap();
-}
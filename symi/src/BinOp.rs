@@ -1,10 +1,12 @@
 //! BinOp : Collect all binary operations available in C. May be extended upto
 //! C++ binops.
 
+use crate::DataType::CDataTypes;
+use crate::Target::TargetInfo;
 use std::fmt;
 
 /// Binary opcodes which BinarySymExpr are allowed to take into account.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Opcode {
     Mul,
     Div,
@@ -27,9 +29,26 @@ pub enum Opcode {
     Assign,
 }
 
+/// Groups of binary operators sharing their typing and evaluation rules.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Category {
+    /// `*`, `/`, `%`, `+` and `-`.
+    Arithmetic,
+    /// `<<` and `>>`.
+    Shift,
+    /// `<`, `>`, `<=`, `>=`, `==` and `!=`.
+    Comparison,
+    /// `&`, `^` and `|`.
+    Bitwise,
+    /// `&&` and `||`.
+    Logical,
+    /// `=`.
+    Assignment,
+}
+
 impl Opcode {
     pub fn getOpcode(opcode: &str) -> Option<Opcode> {
-        return match opcode {
+        match opcode {
             "*" => Some(Self::Mul),
             "/" => Some(Self::Div),
             "%" => Some(Self::Rem),
@@ -50,7 +69,155 @@ impl Opcode {
             "||" => Some(Self::LOr),
             "=" => Some(Self::Assign),
             _ => None,
-        };
+        }
+    }
+
+    /// Binding power of the operator in C, higher binds tighter.
+    pub fn getPrecedence(&self) -> u32 {
+        match self {
+            Opcode::Assign => 1,
+            Opcode::LOr => 2,
+            Opcode::LAnd => 3,
+            Opcode::Or => 4,
+            Opcode::Xor => 5,
+            Opcode::And => 6,
+            Opcode::EQ | Opcode::NE => 7,
+            Opcode::LT | Opcode::GT | Opcode::LE | Opcode::GE => 8,
+            Opcode::Shl | Opcode::Shr => 9,
+            Opcode::Add | Opcode::Sub => 10,
+            Opcode::Mul | Opcode::Div | Opcode::Rem => 11,
+        }
+    }
+
+    /// Whether `a op b op c` groups as `a op (b op c)`. Only assignment does.
+    pub fn isRightAssoc(&self) -> bool {
+        *self == Opcode::Assign
+    }
+
+    pub fn getCategory(&self) -> Category {
+        match self {
+            Opcode::Mul | Opcode::Div | Opcode::Rem | Opcode::Add | Opcode::Sub => {
+                Category::Arithmetic
+            }
+            Opcode::Shl | Opcode::Shr => Category::Shift,
+            Opcode::LT | Opcode::GT | Opcode::LE | Opcode::GE | Opcode::EQ | Opcode::NE => {
+                Category::Comparison
+            }
+            Opcode::And | Opcode::Xor | Opcode::Or => Category::Bitwise,
+            Opcode::LAnd | Opcode::LOr => Category::Logical,
+            Opcode::Assign => Category::Assignment,
+        }
+    }
+
+    pub fn isArithmetic(&self) -> bool {
+        self.getCategory() == Category::Arithmetic
+    }
+
+    pub fn isShift(&self) -> bool {
+        self.getCategory() == Category::Shift
+    }
+
+    pub fn isComparison(&self) -> bool {
+        self.getCategory() == Category::Comparison
+    }
+
+    pub fn isBitwise(&self) -> bool {
+        self.getCategory() == Category::Bitwise
+    }
+
+    pub fn isLogical(&self) -> bool {
+        self.getCategory() == Category::Logical
+    }
+
+    /// Whether C requires integer operands: `%`, shifts and bitwise operators.
+    pub fn needsIntegers(&self) -> bool {
+        *self == Opcode::Rem || self.isShift() || self.isBitwise()
+    }
+
+    /// Whether `a op b` and `b op a` have the same value. For `&&` and `||`
+    /// this only holds when both operands are defined, as the RHS may not be
+    /// evaluated.
+    pub fn isCommutative(&self) -> bool {
+        matches!(
+            self,
+            Opcode::Mul
+                | Opcode::Add
+                | Opcode::EQ
+                | Opcode::NE
+                | Opcode::And
+                | Opcode::Xor
+                | Opcode::Or
+                | Opcode::LAnd
+                | Opcode::LOr
+        )
+    }
+
+    /// Whether `(a op b) op c` and `a op (b op c)` have the same value, for
+    /// operands of one type. Arithmetic wraps around, so this includes `+`
+    /// and `*`. Conversions may differ between the two groupings when the
+    /// operands have different types.
+    pub fn isAssociative(&self) -> bool {
+        matches!(
+            self,
+            Opcode::Mul
+                | Opcode::Add
+                | Opcode::And
+                | Opcode::Xor
+                | Opcode::Or
+                | Opcode::LAnd
+                | Opcode::LOr
+        )
+    }
+
+    /// Type of `LHS op RHS` for operands of types `lty` and `rty`. Comparisons
+    /// and logical operators give an int, shifts the promoted type of their
+    /// LHS, and the others the common type of both operands on `target`.
+    pub fn getResultType(
+        &self,
+        lty: CDataTypes,
+        rty: CDataTypes,
+        target: &TargetInfo,
+    ) -> CDataTypes {
+        match self.getCategory() {
+            Category::Comparison | Category::Logical => CDataTypes::Int,
+            Category::Shift => lty.promote(),
+            Category::Assignment => lty,
+            Category::Arithmetic | Category::Bitwise => lty.getCommonType(rty, target),
+        }
+    }
+
+    /// Whether some operands make the operation undefined: a zero divisor or
+    /// `INT_MIN / -1`, shifts by a negative amount or by the width of the
+    /// type or more, and signed overflow.
+    pub fn mayTriggerUB(&self) -> bool {
+        matches!(self.getCategory(), Category::Arithmetic | Category::Shift)
+    }
+
+    /// Operator giving the same value with both operands swapped, e.g. `>`
+    /// for `<`. None if there is none.
+    pub fn getSwapped(&self) -> Option<Opcode> {
+        match self {
+            Opcode::LT => Some(Opcode::GT),
+            Opcode::GT => Some(Opcode::LT),
+            Opcode::LE => Some(Opcode::GE),
+            Opcode::GE => Some(Opcode::LE),
+            op if op.isCommutative() => Some(*op),
+            _ => None,
+        }
+    }
+
+    /// Comparison giving the logical negation of this one, e.g. `>=` for `<`.
+    /// None for operators which are not comparisons.
+    pub fn getInverse(&self) -> Option<Opcode> {
+        match self {
+            Opcode::LT => Some(Opcode::GE),
+            Opcode::GT => Some(Opcode::LE),
+            Opcode::LE => Some(Opcode::GT),
+            Opcode::GE => Some(Opcode::LT),
+            Opcode::EQ => Some(Opcode::NE),
+            Opcode::NE => Some(Opcode::EQ),
+            _ => None,
+        }
     }
 }
 
@@ -79,3 +246,69 @@ impl fmt::Display for Opcode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Eval::{evalBinary, Value};
+
+    #[test]
+    fn test_Opcode() {
+        let ops = [
+            Opcode::Mul,
+            Opcode::Div,
+            Opcode::Rem,
+            Opcode::Add,
+            Opcode::Sub,
+            Opcode::Shl,
+            Opcode::Shr,
+            Opcode::LT,
+            Opcode::GT,
+            Opcode::LE,
+            Opcode::GE,
+            Opcode::EQ,
+            Opcode::NE,
+            Opcode::And,
+            Opcode::Xor,
+            Opcode::Or,
+            Opcode::LAnd,
+            Opcode::LOr,
+        ];
+        let target = TargetInfo::default();
+        let (int, uint) = (CDataTypes::Int, CDataTypes::UnsignedInt);
+        let vals = [-3, -1, 0, 1, 2, 5];
+
+        for op in ops {
+            assert_eq!(Opcode::getOpcode(&op.to_string()), Some(op));
+            for (l, r) in vals.iter().flat_map(|l| vals.iter().map(move |r| (*l, *r))) {
+                let (lhs, rhs) = (Value::new(l, int, &target), Value::new(r, uint, &target));
+                let Ok(val) = evalBinary(op, lhs, rhs, &target) else {
+                    assert!(op.mayTriggerUB(), "{l} {op} {r}");
+                    continue;
+                };
+                assert_eq!(val.ty, op.getResultType(int, uint, &target));
+                if let Some(swapped) = op.getSwapped() {
+                    let swapped = evalBinary(swapped, rhs, lhs, &target);
+                    assert_eq!(swapped, Ok(val), "{l} {op} {r}");
+                }
+                if let Some(inverse) = op.getInverse() {
+                    let inverted = evalBinary(inverse, lhs, rhs, &target).unwrap();
+                    assert_eq!(inverted.val, 1 - val.val, "{l} {op} {r}");
+                }
+            }
+        }
+
+        assert_eq!(
+            Opcode::Shl.getResultType(CDataTypes::Short, CDataTypes::Long, &target),
+            int
+        );
+        assert_eq!(
+            Opcode::Assign.getResultType(CDataTypes::Short, int, &target),
+            CDataTypes::Short
+        );
+        assert!(Opcode::Sub.isArithmetic() && !Opcode::Sub.isCommutative());
+        assert!(Opcode::Xor.isBitwise() && Opcode::Xor.isAssociative());
+        assert_eq!(Opcode::Assign.getSwapped(), None);
+        assert_eq!(Opcode::LAnd.getInverse(), None);
+    }
+}
@@ -0,0 +1,281 @@
+//! Corner : Corner cases for a pair of symbols and an operator.
+//!
+//! A corner case constrains each symbol of `S1 Op S2` to a window of values.
+//! The matrix of cases is made of:
+//! 1. Where the windows lie: around the minimum, the middle or the maximum of
+//!    the values both types can hold.
+//! 2. How the windows relate: non overlapping `[1, 2]` and `[3, 4]`, partially
+//!    overlapping `[1, 3]` and `[2, 4]`, or completely overlapping `[1, 3]` and
+//!    `[1, 3]`.
+//! 3. What happens to the result of Op: the widest windows (up to an eighth of
+//!    the values) for which it stays in range, and the narrowest ones for which
+//!    it overflows or underflows its type.
+//!
+//! Cases which do not exist for an operator, e.g. an overflowing comparison,
+//! are left out.
+
+use crate::BinOp::Opcode;
+use crate::Expr::{BinarySymExpr, ChainedBSE, Expr, ToExpr};
+use crate::Range::RangeSet;
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::Test::Test;
+use crate::Transfer::{applyExact, AbstractValue};
+use std::sync::Arc;
+
+/// Test of an assertion on S1 and S2 under a pair of windows.
+pub type CornerTest = Test<Expr, Expr, Expr, Expr>;
+
+/// Where the windows lie in the values both types can hold.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Location {
+    Min,
+    Mid,
+    Max,
+}
+
+/// How the window of S1 relates to the window of S2.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Overlap {
+    Disjoint,
+    Partial,
+    Complete,
+}
+
+/// What happens to the result of Op over the windows. The result of a floating
+/// type overflows where it leaves the integers the type holds without gaps,
+/// see CDataTypes::getRange().
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Flow {
+    InRange,
+    Overflow,
+    Underflow,
+}
+
+/// A corner case, the windows [lo, hi] of S1 and S2.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Case {
+    pub location: Location,
+    pub overlap: Overlap,
+    pub flow: Flow,
+    pub LHS: (i128, i128),
+    pub RHS: (i128, i128),
+}
+
+impl Case {
+    /// Returns the conditional constraining S1 and S2 to the windows.
+    pub fn getConditional(
+        &self,
+        S1: &Arc<Sym>,
+        S2: &Arc<Sym>,
+        target: &TargetInfo,
+    ) -> Arc<BinarySymExpr<Expr, Expr>> {
+        conditional(S1, self.LHS, S2, self.RHS, target)
+    }
+
+    /// Returns the test of `S1 Op S2` under the windows.
+    pub fn getTest(
+        &self,
+        S1: &Arc<Sym>,
+        S2: &Arc<Sym>,
+        Op: Opcode,
+        target: &TargetInfo,
+    ) -> Arc<CornerTest> {
+        let Assertion = BinarySymExpr::new(&S1.toExpr(), &S2.toExpr(), Op);
+        Test::new(&self.getConditional(S1, S2, target), &Assertion, target)
+    }
+}
+
+/// Returns the conditional `S1 >= lo1 && S1 <= hi1 && S2 >= lo2 && S2 <= hi2`
+/// for windows [lo1, hi1] and [lo2, hi2] of the values the types of S1 and S2
+/// hold on `target`.
+pub fn conditional(
+    S1: &Arc<Sym>,
+    LHS: (i128, i128),
+    S2: &Arc<Sym>,
+    RHS: (i128, i128),
+    target: &TargetInfo,
+) -> Arc<BinarySymExpr<Expr, Expr>> {
+    let bound = |S: &Sym, val: i128| {
+        Conc::tryNew(val, S.getType(), target).expect("windows lie inside the type")
+    };
+    let C1 = BinarySymExpr::new(S1, &bound(S1, LHS.0), Opcode::GE);
+    let C2 = BinarySymExpr::new(S1, &bound(S1, LHS.1), Opcode::LE);
+    let C3 = BinarySymExpr::new(S2, &bound(S2, RHS.0), Opcode::GE);
+    let C4 = BinarySymExpr::new(S2, &bound(S2, RHS.1), Opcode::LE);
+    ChainedBSE::new(&[&C1, &C2, &C3, &C4], Opcode::LAnd)
+        .join()
+        .unwrap()
+}
+
+/// Returns the matrix of corner cases of `S1 Op S2`. Cases with the same
+/// windows are only listed once.
+pub fn cases(S1: &Sym, S2: &Sym, Op: Opcode, target: &TargetInfo) -> Vec<Case> {
+    let (lo1, hi1) = S1.getTypeRange(target);
+    let (lo2, hi2) = S2.getTypeRange(target);
+    let (lo, hi) = (lo1.max(lo2), hi1.min(hi2));
+    let ResTy = Op.getResultType(S1.getType(), S2.getType(), target);
+    let (R_MIN, R_MAX) = (ResTy.getMin(target), ResTy.getMax(target));
+
+    let mut cases: Vec<Case> = vec![];
+    for location in [Location::Min, Location::Mid, Location::Max] {
+        for overlap in [Overlap::Disjoint, Overlap::Partial, Overlap::Complete] {
+            let mut found = vec![];
+            let (mut fits, mut overflow, mut underflow) = (true, false, false);
+
+            // Windows of 3, 7, 15, ... values, as long as they fit.
+            let mut n = 3;
+            while let Some((LHS, RHS)) = place(location, overlap, n, lo, hi) {
+                let L = AbstractValue::new(RangeSet::new(LHS.0, LHS.1), S1.getType());
+                let R = AbstractValue::new(RangeSet::new(RHS.0, RHS.1), S2.getType());
+                let Res = applyExact(Op, &L, &R, target);
+                let (min, max) = match (Res.getMin(), Res.getMax()) {
+                    (Some(min), Some(max)) => (min, max),
+                    _ => break,
+                };
+                let case = |flow| Case {
+                    location,
+                    overlap,
+                    flow,
+                    LHS,
+                    RHS,
+                };
+
+                // Windows of in range cases are kept below an eighth of the
+                // values, or comparisons would end up covering all of them.
+                if fits && n <= (hi - lo + 1) / 8 && R_MIN <= min && max <= R_MAX {
+                    found.retain(|case: &Case| case.flow != Flow::InRange);
+                    found.push(case(Flow::InRange));
+                } else {
+                    fits = false;
+                }
+                if !overflow && max > R_MAX {
+                    overflow = true;
+                    found.push(case(Flow::Overflow));
+                }
+                if !underflow && min < R_MIN {
+                    underflow = true;
+                    found.push(case(Flow::Underflow));
+                }
+
+                if !fits && overflow && underflow {
+                    break;
+                }
+                n = n * 2 + 1;
+            }
+
+            for case in found {
+                if !cases.iter().any(|c| c.LHS == case.LHS && c.RHS == case.RHS) {
+                    cases.push(case);
+                }
+            }
+        }
+    }
+    cases
+}
+
+/// Returns one test of `S1 Op S2` per corner case.
+pub fn tests(
+    S1: &Arc<Sym>,
+    S2: &Arc<Sym>,
+    Op: Opcode,
+    target: &TargetInfo,
+) -> Vec<Arc<CornerTest>> {
+    cases(S1, S2, Op, target)
+        .iter()
+        .map(|case| case.getTest(S1, S2, Op, target))
+        .collect()
+}
+
+/// Windows of n values each for S1 and S2 in [lo, hi], or None if they do not
+/// fit.
+fn place(
+    location: Location,
+    overlap: Overlap,
+    n: i128,
+    lo: i128,
+    hi: i128,
+) -> Option<((i128, i128), (i128, i128))> {
+    let shift = match overlap {
+        Overlap::Disjoint => n,
+        Overlap::Partial => (n + 1) / 2,
+        Overlap::Complete => 0,
+    };
+    let span = shift + n;
+    if span > hi - lo + 1 {
+        return None;
+    }
+
+    let start = match location {
+        Location::Min => lo,
+        Location::Mid => lo + (hi - lo + 1 - span) / 2,
+        Location::Max => hi - span + 1,
+    };
+    Some((
+        (start, start + n - 1),
+        (start + shift, start + shift + n - 1),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Oracle::Expectation;
+
+    #[test]
+    fn test_cases() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "int");
+        let y = Sym::new("y", "int");
+        let INT_MAX = i32::MAX as i128;
+
+        let add = cases(&x, &y, Opcode::Add, &target);
+        let find = |location, overlap, flow| {
+            add.iter()
+                .find(|c| c.location == location && c.overlap == overlap && c.flow == flow)
+                .cloned()
+        };
+
+        // Anything at INT_MAX overflows right away.
+        let max = find(Location::Max, Overlap::Complete, Flow::Overflow).unwrap();
+        assert_eq!(max.LHS, (INT_MAX - 2, INT_MAX));
+        assert_eq!(max.RHS, (INT_MAX - 2, INT_MAX));
+        assert!(find(Location::Max, Overlap::Complete, Flow::InRange).is_none());
+
+        let min = find(Location::Min, Overlap::Disjoint, Flow::Underflow).unwrap();
+        assert_eq!(min.LHS, (-INT_MAX - 1, -INT_MAX + 1));
+        assert_eq!(min.RHS, (-INT_MAX + 2, -INT_MAX + 4));
+
+        // The widest windows around 0 whose sum stays in range.
+        let mid = find(Location::Mid, Overlap::Partial, Flow::InRange).unwrap();
+        assert!(mid.LHS.0 < 0 && mid.RHS.1 > 0);
+        assert!(mid.LHS.0 + mid.RHS.0 >= -INT_MAX - 1 && mid.LHS.1 + mid.RHS.1 <= INT_MAX);
+        assert!(find(Location::Mid, Overlap::Partial, Flow::Overflow).is_none());
+
+        // Products around 0 do overflow.
+        let mul = cases(&x, &y, Opcode::Mul, &target);
+        assert!(mul
+            .iter()
+            .any(|c| c.location == Location::Mid && c.flow == Flow::Overflow));
+
+        // Comparisons never overflow.
+        let lt = cases(&x, &y, Opcode::LT, &target);
+        assert_eq!(lt.len(), 9);
+        assert!(lt.iter().all(|c| c.flow == Flow::InRange));
+    }
+
+    #[test]
+    fn test_tests() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "unsigned char");
+        let y = Sym::new("y", "int");
+
+        let tests = tests(&x, &y, Opcode::LT, &target);
+        assert_eq!(tests.len(), 9);
+        // Disjoint windows of x below those of y.
+        assert_eq!(tests[0].getExpectation(), Some(Expectation::True));
+        assert!(tests[0]
+            .to_string()
+            .contains("if (x >= 0 && x <= 30 && y >= 31 && y <= 61)"));
+    }
+}
@@ -1,6 +1,7 @@
 //! DataType : All available data types in C are structured here.
 //!
 
+use crate::Target::{LongDoubleFormat, TargetInfo};
 use std::fmt;
 
 // If there are n possible types (for which assertions are to be constructed),
@@ -13,7 +14,7 @@ use std::fmt;
 // For each type i, there will be pairs with other types (constrained previously
 // types are not paired).
 // TODO: Rename to _builtin (will provide custom data type interface later)
-#[derive(Copy, Clone, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
 pub enum CDataTypes {
     Char,
     SignedChar,
@@ -22,9 +23,6 @@ pub enum CDataTypes {
     UnsignedShort,
     Int,
     UnsignedInt,
-    // TODO
-    // long, unsigned long, long long, unsigned long long( int), float, double,
-    // long double
     Long,
     UnsignedLong,
     LongLong,
@@ -36,7 +34,7 @@ pub enum CDataTypes {
 
 impl CDataTypes {
     pub fn getType(dtype: &str) -> Option<CDataTypes> {
-        return match dtype {
+        match dtype {
             "char" => Some(CDataTypes::Char),
             "signed char" => Some(CDataTypes::SignedChar),
             "unsigned char" => Some(CDataTypes::UnsignedChar),
@@ -53,28 +51,307 @@ impl CDataTypes {
             "double" => Some(CDataTypes::Double),
             "long double" => Some(CDataTypes::LongDouble),
             _ => None,
+        }
+    }
+
+    /// Limits of an integer type on `target`, as given by limits.h. The
+    /// values are i128 wide, which holds both LLONG_MIN and ULLONG_MAX.
+    ///
+    /// The values symi models of a floating type are integers, and its range
+    /// is [-2^digits, 2^digits] of its format: all the integers it holds
+    /// without gaps. Larger integers are rounded, see wrap(). The limits of
+    /// floating types, as given by float.h, lie far beyond, see getLimits().
+    pub fn getRange(&self, target: &TargetInfo) -> (i128, i128) {
+        match self {
+            CDataTypes::Char if target.charIsSigned => (i8::MIN as i128, i8::MAX as i128),
+            CDataTypes::Char => (0, u8::MAX as i128),
+            CDataTypes::Long | CDataTypes::UnsignedLong if target.getLongWidth() == 32 => {
+                self.getPortableRange()
+            }
+            CDataTypes::Long => (i64::MIN as i128, i64::MAX as i128),
+            CDataTypes::UnsignedLong => (u64::MIN as i128, u64::MAX as i128),
+            CDataTypes::LongDouble => longDoubleFormat(target).getIntegerRange(),
+            _ => self.getPortableRange(),
+        }
+    }
+
+    /// Values a type holds on every target: plain char may be unsigned, long
+    /// may be as narrow as int, and long double as narrow as double.
+    pub fn getPortableRange(&self) -> (i128, i128) {
+        match self {
+            CDataTypes::Char => (0, i8::MAX as i128),
+            CDataTypes::SignedChar => (i8::MIN as i128, i8::MAX as i128),
+            CDataTypes::UnsignedChar => (0, u8::MAX as i128),
+            CDataTypes::Short => (i16::MIN as i128, i16::MAX as i128),
+            CDataTypes::UnsignedShort => (u16::MIN as i128, u16::MAX as i128),
+            CDataTypes::Int | CDataTypes::Long => (i32::MIN as i128, i32::MAX as i128),
+            CDataTypes::UnsignedInt | CDataTypes::UnsignedLong => {
+                (u32::MIN as i128, u32::MAX as i128)
+            }
+            CDataTypes::LongLong => (i64::MIN as i128, i64::MAX as i128),
+            CDataTypes::UnsignedLongLong => (u64::MIN as i128, u64::MAX as i128),
+            CDataTypes::Float => FloatFormat::BINARY32.getIntegerRange(),
+            CDataTypes::Double | CDataTypes::LongDouble => FloatFormat::BINARY64.getIntegerRange(),
+        }
+    }
+
+    /// Format of a floating type on `target`, None for integer types.
+    pub fn getFloatFormat(&self, target: &TargetInfo) -> Option<FloatFormat> {
+        match self {
+            CDataTypes::Float => Some(FloatFormat::BINARY32),
+            CDataTypes::Double => Some(FloatFormat::BINARY64),
+            CDataTypes::LongDouble => Some(longDoubleFormat(target)),
+            _ => None,
+        }
+    }
+
+    /// Limits of any type on `target`, as given by limits.h and float.h.
+    pub fn getLimits(&self, target: &TargetInfo) -> (Limit, Limit) {
+        let (min, max) = match self {
+            CDataTypes::Float => ("-3.40282347e+38F", "3.40282347e+38F"),
+            CDataTypes::Double => ("-1.7976931348623157e+308", "1.7976931348623157e+308"),
+            CDataTypes::LongDouble => match target.longDouble {
+                LongDoubleFormat::Double => {
+                    ("-1.7976931348623157e+308L", "1.7976931348623157e+308L")
+                }
+                LongDoubleFormat::X87 => (
+                    "-1.18973149535723176502e+4932L",
+                    "1.18973149535723176502e+4932L",
+                ),
+                LongDoubleFormat::Quad => (
+                    "-1.18973149535723176508575932662800702e+4932L",
+                    "1.18973149535723176508575932662800702e+4932L",
+                ),
+            },
+            _ => {
+                let (T_MIN, T_MAX) = self.getRange(target);
+                return (Limit::Integer(T_MIN), Limit::Integer(T_MAX));
+            }
+        };
+        (Limit::Floating(min), Limit::Floating(max))
+    }
+
+    /// Returns true for the integer types, i.e. everything but float, double
+    /// and long double.
+    pub fn isInteger(&self) -> bool {
+        !matches!(
+            self,
+            CDataTypes::Float | CDataTypes::Double | CDataTypes::LongDouble
+        )
+    }
+
+    /// Signedness of the type. Plain char follows `target`.
+    pub fn isSigned(&self, target: &TargetInfo) -> bool {
+        if *self == CDataTypes::Char {
+            return target.charIsSigned;
+        }
+
+        !matches!(
+            self,
+            CDataTypes::UnsignedChar
+                | CDataTypes::UnsignedShort
+                | CDataTypes::UnsignedInt
+                | CDataTypes::UnsignedLong
+                | CDataTypes::UnsignedLongLong
+        )
+    }
+
+    /// Width of the type in bits on `target`.
+    pub fn getWidth(&self, target: &TargetInfo) -> u32 {
+        match self {
+            CDataTypes::Long | CDataTypes::UnsignedLong => target.getLongWidth(),
+            CDataTypes::LongDouble => target.longDoubleWidth,
+            CDataTypes::Char | CDataTypes::SignedChar | CDataTypes::UnsignedChar => 8,
+            CDataTypes::Short | CDataTypes::UnsignedShort => 16,
+            CDataTypes::Int | CDataTypes::UnsignedInt | CDataTypes::Float => 32,
+            CDataTypes::LongLong | CDataTypes::UnsignedLongLong | CDataTypes::Double => 64,
+        }
+    }
+
+    /// Integer conversion rank (C11 6.3.1.1). Floating types have no rank and
+    /// return 0.
+    pub fn getRank(&self) -> u32 {
+        match self {
+            CDataTypes::Char | CDataTypes::SignedChar | CDataTypes::UnsignedChar => 1,
+            CDataTypes::Short | CDataTypes::UnsignedShort => 2,
+            CDataTypes::Int | CDataTypes::UnsignedInt => 3,
+            CDataTypes::Long | CDataTypes::UnsignedLong => 4,
+            CDataTypes::LongLong | CDataTypes::UnsignedLongLong => 5,
+            _ => 0,
+        }
+    }
+
+    /// Returns the unsigned type corresponding to this integer type.
+    pub fn toUnsigned(&self) -> CDataTypes {
+        match self {
+            CDataTypes::Char | CDataTypes::SignedChar => CDataTypes::UnsignedChar,
+            CDataTypes::Short => CDataTypes::UnsignedShort,
+            CDataTypes::Int => CDataTypes::UnsignedInt,
+            CDataTypes::Long => CDataTypes::UnsignedLong,
+            CDataTypes::LongLong => CDataTypes::UnsignedLongLong,
+            _ => *self,
+        }
+    }
+
+    /// Integer promotion: every type whose rank is below int is promoted to
+    /// int, since int can represent all of their values.
+    pub fn promote(&self) -> CDataTypes {
+        if self.isInteger() && self.getRank() < CDataTypes::Int.getRank() {
+            CDataTypes::Int
+        } else {
+            *self
+        }
+    }
+
+    /// The usual arithmetic conversions (C11 6.3.1.8). Returns the common type
+    /// both operands of a binary operator are converted to on `target`.
+    pub fn getCommonType(&self, other: CDataTypes, target: &TargetInfo) -> CDataTypes {
+        if !self.isInteger() || !other.isInteger() {
+            // The wider floating type wins, which is the latter in the enum.
+            return if self > &other { *self } else { other };
+        }
+
+        let (lhs, rhs) = (self.promote(), other.promote());
+        if lhs == rhs {
+            return lhs;
+        }
+
+        if lhs.isSigned(target) == rhs.isSigned(target) {
+            return if lhs.getRank() >= rhs.getRank() {
+                lhs
+            } else {
+                rhs
+            };
+        }
+
+        let (signed, unsigned) = if lhs.isSigned(target) {
+            (lhs, rhs)
+        } else {
+            (rhs, lhs)
         };
+        if unsigned.getRank() >= signed.getRank() {
+            unsigned
+        } else if signed.getWidth(target) > unsigned.getWidth(target) {
+            signed
+        } else {
+            signed.toUnsigned()
+        }
+    }
+
+    /// Smallest value of the range of the type on `target`, see getRange().
+    pub fn getMin(&self, target: &TargetInfo) -> i128 {
+        self.getRange(target).0
+    }
+
+    /// Largest value of the range of the type on `target`, see getRange().
+    pub fn getMax(&self, target: &TargetInfo) -> i128 {
+        self.getRange(target).1
+    }
+
+    /// Convert an integer value to this type, wrapping it modulo 2^width the
+    /// way a two's complement target does, or rounding it to the nearest value
+    /// of a floating type.
+    pub fn wrap(&self, val: i128, target: &TargetInfo) -> i128 {
+        if let Some(format) = self.getFloatFormat(target) {
+            return format.round(val);
+        }
+
+        let width = self.getWidth(target);
+        let mask = (1i128 << width) - 1;
+        let val = val & mask;
+        if self.isSigned(target) && val >> (width - 1) != 0 {
+            val - (1i128 << width)
+        } else {
+            val
+        }
     }
+}
+
+/// An IEEE 754 binary format, or the x87 extended one, by the parameters
+/// float.h gives of it: the bits of the significand (FLT_MANT_DIG) and the
+/// largest exponent (FLT_MAX_EXP). Its largest value is
+/// `(1 - 2^-digits) * 2^maxExp`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FloatFormat {
+    pub digits: u32,
+    pub maxExp: u32,
+}
+
+impl FloatFormat {
+    pub const BINARY32: FloatFormat = FloatFormat {
+        digits: 24,
+        maxExp: 128,
+    };
+    pub const BINARY64: FloatFormat = FloatFormat {
+        digits: 53,
+        maxExp: 1024,
+    };
+    /// x87 80-bit extended precision.
+    pub const X87: FloatFormat = FloatFormat {
+        digits: 64,
+        maxExp: 16384,
+    };
+    pub const BINARY128: FloatFormat = FloatFormat {
+        digits: 113,
+        maxExp: 16384,
+    };
 
-    // FIXME: We want to return tuples of various types.
-    // 1. Use Option
-    //
-    // Instead of returning builtin types, we can return Conc<T> and implement
-    // shifting operators over those types.
-    pub fn getRange(&self) -> (i64, i64) {
+    /// The integers of magnitude up to 2^digits, which the format holds
+    /// without gaps.
+    pub fn getIntegerRange(&self) -> (i128, i128) {
+        (-(1i128 << self.digits), 1i128 << self.digits)
+    }
+
+    /// Round an integer to the nearest value of the format, ties to even as in
+    /// the default rounding mode. No i128 exceeds the largest value of any
+    /// format, but 2^127 itself does not fit back and saturates.
+    pub fn round(&self, val: i128) -> i128 {
+        let magnitude = val.unsigned_abs();
+        let bits = 128 - magnitude.leading_zeros();
+        if bits <= self.digits {
+            return val;
+        }
+
+        let shift = bits - self.digits;
+        let (kept, dropped) = (magnitude >> shift, magnitude & ((1 << shift) - 1));
+        let half = 1u128 << (shift - 1);
+        let kept = if dropped > half || (dropped == half && kept & 1 == 1) {
+            kept + 1
+        } else {
+            kept
+        };
+        let rounded = i128::try_from(kept << shift).unwrap_or(i128::MAX);
+        if val < 0 {
+            -rounded
+        } else {
+            rounded
+        }
+    }
+}
+
+/// Format of long double on `target`.
+fn longDoubleFormat(target: &TargetInfo) -> FloatFormat {
+    match target.longDouble {
+        LongDoubleFormat::Double => FloatFormat::BINARY64,
+        LongDoubleFormat::X87 => FloatFormat::X87,
+        LongDoubleFormat::Quad => FloatFormat::BINARY128,
+    }
+}
+
+/// Smallest or largest value of a type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Limit {
+    Integer(i128),
+    /// Floating limits are kept as spelled in float.h, since LDBL_MAX of x87
+    /// does not fit in any Rust floating type.
+    Floating(&'static str),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            CDataTypes::Char => (0, u8::MAX as i64), // FIXME: Char's type is
-            // dependent and we need to
-            // redefine it based on
-            // unsigned/signed-ness
-            // Look at limits.h
-            CDataTypes::SignedChar => (i8::MIN as i64, i8::MAX as i64),
-            CDataTypes::UnsignedChar => (0, u8::MAX as i64),
-            CDataTypes::Short => (i16::MIN as i64, i16::MAX as i64),
-            CDataTypes::UnsignedShort => (u16::MIN as i64, u16::MAX as i64),
-            CDataTypes::Int => (i32::MIN as i64, i32::MAX as i64),
-            CDataTypes::UnsignedInt => (u32::MIN as i64, u32::MAX as i64),
-            _ => unreachable!(),
+            Limit::Integer(val) => write!(f, "{}", val),
+            Limit::Floating(val) => write!(f, "{}", val),
         }
     }
 }
@@ -130,3 +407,93 @@ impl fmt::Display for CDataTypes {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_getRange() {
+        let target = TargetInfo::default();
+        assert_eq!(
+            CDataTypes::Long.getRange(&target),
+            (i64::MIN as i128, i64::MAX as i128)
+        );
+        assert_eq!(
+            CDataTypes::UnsignedLongLong.getMax(&target),
+            u64::MAX as i128
+        );
+        assert_eq!(
+            CDataTypes::Long.getPortableRange(),
+            (i32::MIN as i128, i32::MAX as i128)
+        );
+        assert_eq!(CDataTypes::Char.getPortableRange(), (0, 127));
+        assert_eq!(
+            CDataTypes::Float.getLimits(&target),
+            (
+                Limit::Floating("-3.40282347e+38F"),
+                Limit::Floating("3.40282347e+38F")
+            )
+        );
+        assert_eq!(
+            CDataTypes::Short.getLimits(&target).0.to_string(),
+            i16::MIN.to_string()
+        );
+
+        // Floats hold every integer up to 2^24, long double of x86 up to 2^64.
+        assert_eq!(CDataTypes::Float.getRange(&target), (-(1 << 24), 1 << 24));
+        assert_eq!(CDataTypes::LongDouble.getMax(&target), 1 << 64);
+        assert_eq!(CDataTypes::LongDouble.getPortableRange().1, 1 << 53);
+        let win = TargetInfo::new("x86_64-pc-windows-msvc").unwrap();
+        assert_eq!(
+            CDataTypes::LongDouble.getFloatFormat(&win),
+            Some(FloatFormat::BINARY64)
+        );
+        assert_eq!(CDataTypes::Int.getFloatFormat(&target), None);
+    }
+
+    #[test]
+    fn test_FloatFormat() {
+        // The largest values agree with float.h.
+        let max = |format: FloatFormat| {
+            (1.0 - 2f64.powi(-(format.digits as i32))) * 2f64.powi(format.maxExp as i32 - 1) * 2.0
+        };
+        assert_eq!(max(FloatFormat::BINARY32), f32::MAX as f64);
+        assert_eq!(max(FloatFormat::BINARY64), f64::MAX);
+
+        let target = TargetInfo::default();
+        let float = CDataTypes::Float;
+        assert_eq!(float.wrap((1 << 24) + 1, &target), 1 << 24);
+        assert_eq!(float.wrap((1 << 24) + 3, &target), (1 << 24) + 4);
+        assert_eq!(float.wrap(-(1 << 25) - 3, &target), -(1 << 25) - 4);
+        assert_eq!(float.wrap(i32::MAX as i128, &target), 1 << 31);
+        assert_eq!(
+            CDataTypes::Double.wrap(i32::MAX as i128, &target),
+            i32::MAX as i128
+        );
+        assert_eq!(float.wrap(i128::MAX, &target), i128::MAX);
+    }
+
+    #[test]
+    fn test_getCommonType() {
+        let target = TargetInfo::default();
+        let (int, uint) = (CDataTypes::Int, CDataTypes::UnsignedInt);
+        assert_eq!(
+            CDataTypes::Short.getCommonType(CDataTypes::UnsignedChar, &target),
+            int
+        );
+        assert_eq!(int.getCommonType(uint, &target), uint);
+        assert_eq!(
+            CDataTypes::Long.getCommonType(uint, &target),
+            CDataTypes::Long
+        );
+        assert_eq!(
+            CDataTypes::LongLong.getCommonType(CDataTypes::UnsignedLong, &target),
+            CDataTypes::UnsignedLongLong
+        );
+        assert_eq!(
+            int.getCommonType(CDataTypes::Double, &target),
+            CDataTypes::Double
+        );
+    }
+}
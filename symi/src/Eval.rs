@@ -0,0 +1,497 @@
+//! Eval : Concrete evaluation of symbolic expressions following C semantics.
+//!
+//! Every symbol of an expression tree is given a concrete value through an
+//! environment, and the tree is folded bottom-up. Each node applies the integer
+//! promotions and the usual arithmetic conversions to its operands, exactly as
+//! a C compiler would, and truncates the result to the node's type.
+//!
+//! Values of floating types are integers, which are rounded to their type like
+//! IEEE 754 arithmetic does. Where a floating result is no integer, e.g. of
+//! `1.0F / 2`, the expression is not evaluated.
+//!
+//! # Examples
+//! ```
+//! use symi::BinOp::Opcode;
+//! use symi::DataType::CDataTypes;
+//! use symi::Eval::{Env, Eval};
+//! use symi::Expr::BinarySymExpr;
+//! use symi::Symbol::{Conc, Sym};
+//! use symi::Target::TargetInfo;
+//!
+//! let u1 = Sym::new("u1", "unsigned int");
+//! let u2 = Sym::new("u2", "unsigned int");
+//! let c50 = Conc::new(50, CDataTypes::Int);
+//! let rem = BinarySymExpr::new(&u1, &u2, Opcode::Rem);
+//! let lt = BinarySymExpr::new(&rem, &c50, Opcode::LT);
+//!
+//! let env = Env::from([("u1".to_string(), 170), ("u2".to_string(), 100)]);
+//! let target = TargetInfo::default();
+//! assert_eq!(lt.eval(&env, &target).unwrap().val, 0);
+//! ```
+
+use crate::BinOp::Opcode;
+use crate::DataType::CDataTypes;
+use crate::Expr::{BinarySymExpr, Expr, UnarySymExpr};
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::UnOp::UnaryOpcode;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A concrete assignment of symbols, mapping symbol names to their values.
+/// Values are converted to the symbol's type on lookup.
+pub type Env = HashMap<String, i128>;
+
+/// A concrete value computed by the evaluator along with its C type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Value {
+    pub ty: CDataTypes,
+    pub val: i128,
+}
+
+impl Value {
+    /// Returns a value of type `ty`, wrapping `val` if it does not fit in it
+    /// on `target`.
+    pub fn new(val: i128, ty: CDataTypes, target: &TargetInfo) -> Value {
+        Value {
+            ty,
+            val: ty.wrap(val, target),
+        }
+    }
+
+    /// Convert this value to another type.
+    pub fn cast(&self, ty: CDataTypes, target: &TargetInfo) -> Value {
+        Value::new(self.val, ty, target)
+    }
+
+    /// Convert this value to another type as a C conversion does. Converting
+    /// a floating value an integer type does not hold is undefined (C11
+    /// 6.3.1.4), which is not modeled.
+    pub fn convert(&self, ty: CDataTypes, target: &TargetInfo) -> Result<Value, EvalError> {
+        if !self.ty.isInteger() && ty.isInteger() {
+            let (T_MIN, T_MAX) = ty.getRange(target);
+            if self.val < T_MIN || self.val > T_MAX {
+                return Err(EvalError::Unsupported(ty));
+            }
+        }
+        Ok(self.cast(ty, target))
+    }
+
+    /// Truthiness of the value as used by a C conditional.
+    pub fn isTrue(&self) -> bool {
+        self.val != 0
+    }
+
+    fn fromBool(b: bool) -> Value {
+        Value {
+            ty: CDataTypes::Int,
+            val: b as i128,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.val)
+    }
+}
+
+/// Reasons an expression cannot be evaluated to a value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvalError {
+    /// A symbol has no value in the environment.
+    Unbound(String),
+    /// Right operand of `/` or `%` is zero.
+    DivByZero,
+    /// Shift amount is negative or not less than the width of the type.
+    InvalidShift,
+    /// A value of this type is not modeled: a floating value which is no
+    /// integer, a floating operand of an operator which needs integers, or a
+    /// conversion of a floating value an integer type does not hold.
+    Unsupported(CDataTypes),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Unbound(name) => write!(f, "symbol {} is not assigned", name),
+            EvalError::DivByZero => write!(f, "division by zero"),
+            EvalError::InvalidShift => write!(f, "shift amount out of range"),
+            EvalError::Unsupported(ty) => write!(f, "cannot evaluate this value of type {}", ty),
+        }
+    }
+}
+
+/// A leaf of an expression tree.
+pub enum Leaf<'a> {
+    Sym(&'a Sym),
+    Conc(Value),
+}
+
+/// Types which can be evaluated to a concrete value.
+pub trait Eval {
+    /// Evaluate the expression as compiled for `target`.
+    fn eval(&self, env: &Env, target: &TargetInfo) -> Result<Value, EvalError>;
+
+    /// Collect all leaves of the expression in left-to-right order.
+    fn leaves<'a>(&'a self, out: &mut Vec<Leaf<'a>>, target: &TargetInfo);
+}
+
+impl Eval for Sym {
+    fn eval(&self, env: &Env, target: &TargetInfo) -> Result<Value, EvalError> {
+        match env.get(&self.name) {
+            Some(val) => Ok(Value::new(*val, self.ty, target)),
+            None => Err(EvalError::Unbound(self.name.clone())),
+        }
+    }
+
+    fn leaves<'a>(&'a self, out: &mut Vec<Leaf<'a>>, _target: &TargetInfo) {
+        out.push(Leaf::Sym(self));
+    }
+}
+
+impl Eval for Conc {
+    fn eval(&self, _env: &Env, _target: &TargetInfo) -> Result<Value, EvalError> {
+        Ok(self.getValue())
+    }
+
+    fn leaves<'a>(&'a self, out: &mut Vec<Leaf<'a>>, _target: &TargetInfo) {
+        out.push(Leaf::Conc(self.getValue()));
+    }
+}
+
+impl Eval for Expr {
+    fn eval(&self, env: &Env, target: &TargetInfo) -> Result<Value, EvalError> {
+        match self {
+            Expr::Sym(sym) => sym.eval(env, target),
+            Expr::Conc(conc) => conc.eval(env, target),
+            Expr::Binary(bse) => bse.eval(env, target),
+            Expr::Unary(unary) => unary.eval(env, target),
+        }
+    }
+
+    fn leaves<'a>(&'a self, out: &mut Vec<Leaf<'a>>, target: &TargetInfo) {
+        match self {
+            Expr::Sym(sym) => sym.leaves(out, target),
+            Expr::Conc(conc) => conc.leaves(out, target),
+            Expr::Binary(bse) => bse.leaves(out, target),
+            Expr::Unary(unary) => unary.leaves(out, target),
+        }
+    }
+}
+
+impl<T1, T2> Eval for BinarySymExpr<T1, T2>
+where
+    T1: Eval,
+    T2: Eval,
+{
+    fn eval(&self, env: &Env, target: &TargetInfo) -> Result<Value, EvalError> {
+        let lhs = self.getLHS().eval(env, target)?;
+
+        // Logical operators short-circuit, the RHS is not evaluated at all if
+        // the LHS decides the result.
+        match self.getOpcode() {
+            Opcode::LAnd if !lhs.isTrue() => return Ok(Value::fromBool(false)),
+            Opcode::LOr if lhs.isTrue() => return Ok(Value::fromBool(true)),
+            _ => {}
+        }
+
+        let rhs = self.getRHS().eval(env, target)?;
+        evalBinary(self.getOpcode(), lhs, rhs, target)
+    }
+
+    fn leaves<'a>(&'a self, out: &mut Vec<Leaf<'a>>, target: &TargetInfo) {
+        self.getLHS().leaves(out, target);
+        self.getRHS().leaves(out, target);
+    }
+}
+
+impl<T> Eval for UnarySymExpr<T>
+where
+    T: Eval,
+{
+    fn eval(&self, env: &Env, target: &TargetInfo) -> Result<Value, EvalError> {
+        evalUnary(
+            self.getOpcode(),
+            self.getOperand().eval(env, target)?,
+            target,
+        )
+    }
+
+    fn leaves<'a>(&'a self, out: &mut Vec<Leaf<'a>>, target: &TargetInfo) {
+        self.getOperand().leaves(out, target);
+
+        // Values wrap around at the limits of the type cast to, which makes
+        // them as interesting as the constants of the expression.
+        if let UnaryOpcode::Cast(ty) = self.getOpcode() {
+            if ty.isInteger() {
+                out.push(Leaf::Conc(Value::new(ty.getMin(target), ty, target)));
+                out.push(Leaf::Conc(Value::new(ty.getMax(target), ty, target)));
+            }
+        }
+    }
+}
+
+/// Apply unary operator `op` on a concrete value.
+pub fn evalUnary(op: UnaryOpcode, operand: Value, target: &TargetInfo) -> Result<Value, EvalError> {
+    let ty = operand.ty.promote();
+    let val = operand.cast(ty, target).val;
+    match op {
+        UnaryOpcode::Neg if !ty.isInteger() => match val.checked_neg() {
+            Some(val) => Ok(Value::new(val, ty, target)),
+            None => Err(EvalError::Unsupported(ty)),
+        },
+        UnaryOpcode::Neg => Ok(Value::new(val.wrapping_neg(), ty, target)),
+        UnaryOpcode::Not if !ty.isInteger() => Err(EvalError::Unsupported(ty)),
+        UnaryOpcode::Not => Ok(Value::new(!val, ty, target)),
+        UnaryOpcode::LNot => Ok(Value::fromBool(!operand.isTrue())),
+        UnaryOpcode::Cast(ty) => operand.convert(ty, target),
+    }
+}
+
+/// Apply binary operator `op` on two concrete values. Signed overflow wraps
+/// around as the analyzer models it, the remaining undefined cases are
+/// reported as errors.
+pub fn evalBinary(
+    op: Opcode,
+    lhs: Value,
+    rhs: Value,
+    target: &TargetInfo,
+) -> Result<Value, EvalError> {
+    if op.needsIntegers() {
+        if let Some(ty) = [lhs.ty, rhs.ty].into_iter().find(|ty| !ty.isInteger()) {
+            return Err(EvalError::Unsupported(ty));
+        }
+    }
+
+    match op {
+        Opcode::Shl | Opcode::Shr => {
+            // Operands of shifts are promoted independently, and the result
+            // has the type of the promoted LHS.
+            let ty = lhs.ty.promote();
+            let (l, r) = (
+                lhs.cast(ty, target).val,
+                rhs.cast(rhs.ty.promote(), target).val,
+            );
+            if r < 0 || r >= ty.getWidth(target) as i128 {
+                return Err(EvalError::InvalidShift);
+            }
+
+            let val = match op {
+                Opcode::Shl => l.wrapping_shl(r as u32),
+                _ => l >> r,
+            };
+            Ok(Value::new(val, ty, target))
+        }
+        Opcode::LAnd => Ok(Value::fromBool(lhs.isTrue() && rhs.isTrue())),
+        Opcode::LOr => Ok(Value::fromBool(lhs.isTrue() || rhs.isTrue())),
+        Opcode::Assign => rhs.convert(lhs.ty, target),
+        _ => {
+            let ty = lhs.ty.getCommonType(rhs.ty, target);
+            let (l, r) = (lhs.cast(ty, target).val, rhs.cast(ty, target).val);
+            if !ty.isInteger() {
+                return evalFloating(op, l, r, ty, target);
+            }
+
+            let val = match op {
+                Opcode::Mul => l.wrapping_mul(r),
+                Opcode::Div | Opcode::Rem if r == 0 => return Err(EvalError::DivByZero),
+                // Both truncate towards zero, as in C.
+                Opcode::Div => l.wrapping_div(r),
+                Opcode::Rem => l.wrapping_rem(r),
+                Opcode::Add => l.wrapping_add(r),
+                Opcode::Sub => l.wrapping_sub(r),
+                Opcode::And => l & r,
+                Opcode::Xor => l ^ r,
+                Opcode::Or => l | r,
+                _ => return Ok(compare(op, l, r)),
+            };
+            Ok(Value::new(val, ty, target))
+        }
+    }
+}
+
+/// Apply arithmetic or comparison operator `op` on two values of floating type
+/// `ty`. The exact result is rounded once, as IEEE 754 requires. Quotients
+/// which are no integers, and results too large for i128, are not modeled.
+fn evalFloating(
+    op: Opcode,
+    l: i128,
+    r: i128,
+    ty: CDataTypes,
+    target: &TargetInfo,
+) -> Result<Value, EvalError> {
+    let val = match op {
+        Opcode::Mul => l.checked_mul(r),
+        Opcode::Div if r == 0 || l % r != 0 => None,
+        Opcode::Div => Some(l / r),
+        Opcode::Add => l.checked_add(r),
+        Opcode::Sub => l.checked_sub(r),
+        _ => return Ok(compare(op, l, r)),
+    };
+    match val {
+        Some(val) if val.unsigned_abs() < 1 << 126 => Ok(Value::new(val, ty, target)),
+        _ => Err(EvalError::Unsupported(ty)),
+    }
+}
+
+/// Apply comparison operator `op` on two values of the same type.
+fn compare(op: Opcode, l: i128, r: i128) -> Value {
+    Value::fromBool(match op {
+        Opcode::LT => l < r,
+        Opcode::GT => l > r,
+        Opcode::LE => l <= r,
+        Opcode::GE => l >= r,
+        Opcode::EQ => l == r,
+        Opcode::NE => l != r,
+        _ => unreachable!(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vals: &[(&str, i128)]) -> Env {
+        vals.iter().map(|(n, v)| (n.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn test_evalRemLT() {
+        let target = TargetInfo::default();
+        let u1 = Sym::new("u1", "unsigned int");
+        let u2 = Sym::new("u2", "unsigned int");
+        let c50 = Conc::new(50, CDataTypes::Int);
+        let rem = BinarySymExpr::new(&u1, &u2, Opcode::Rem);
+        let lt = BinarySymExpr::new(&rem, &c50, Opcode::LT);
+
+        assert_eq!(
+            lt.eval(&env(&[("u1", 149), ("u2", 100)]), &target)
+                .unwrap()
+                .val,
+            1
+        );
+        assert_eq!(
+            lt.eval(&env(&[("u1", 150), ("u2", 100)]), &target)
+                .unwrap()
+                .val,
+            0
+        );
+        assert_eq!(
+            rem.eval(&env(&[("u1", 1), ("u2", 0)]), &target),
+            Err(EvalError::DivByZero)
+        );
+        assert_eq!(
+            lt.eval(&env(&[("u1", 1)]), &target),
+            Err(EvalError::Unbound("u2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_evalUnary() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "int");
+        let uc = Sym::new("uc", "unsigned char");
+        let u = Sym::new("u", "unsigned int");
+
+        // (int)(unsigned char)x
+        let cast = UnarySymExpr::new(&x, UnaryOpcode::Cast(CDataTypes::UnsignedChar));
+        let cast = UnarySymExpr::new(&cast, UnaryOpcode::Cast(CDataTypes::Int));
+        assert_eq!(
+            cast.eval(&env(&[("x", -1)]), &target).unwrap(),
+            Value::new(255, CDataTypes::Int, &target)
+        );
+        assert_eq!(cast.eval(&env(&[("x", 256)]), &target).unwrap().val, 0);
+
+        // Operands are promoted first.
+        let neg = UnarySymExpr::new(&uc, UnaryOpcode::Neg);
+        assert_eq!(
+            neg.eval(&env(&[("uc", 255)]), &target).unwrap(),
+            Value::new(-255, CDataTypes::Int, &target)
+        );
+        let not = UnarySymExpr::new(&u, UnaryOpcode::Not);
+        assert_eq!(
+            not.eval(&env(&[("u", 0)]), &target).unwrap().val,
+            u32::MAX as i128
+        );
+        let neg = UnarySymExpr::new(&x, UnaryOpcode::Neg);
+        assert_eq!(
+            neg.eval(&env(&[("x", i32::MIN as i128)]), &target)
+                .unwrap()
+                .val,
+            i32::MIN as i128
+        );
+
+        let lnot = UnarySymExpr::new(&u, UnaryOpcode::LNot);
+        assert_eq!(lnot.eval(&env(&[("u", 7)]), &target).unwrap().val, 0);
+        assert_eq!(lnot.eval(&env(&[("u", 0)]), &target).unwrap().val, 1);
+    }
+
+    #[test]
+    fn test_evalConversions() {
+        let target = TargetInfo::default();
+        let s = Sym::new("s", "int");
+        let u = Sym::new("u", "unsigned int");
+        let uch = Sym::new("uch", "unsigned char");
+        let sch = Sym::new("sch", "signed char");
+
+        // -1 is converted to UINT_MAX.
+        let lt = BinarySymExpr::new(&s, &u, Opcode::LT);
+        assert_eq!(
+            lt.eval(&env(&[("s", -1), ("u", 0)]), &target).unwrap().val,
+            0
+        );
+
+        // Narrow types are promoted to int, no wraparound happens.
+        let add = BinarySymExpr::new(&uch, &sch, Opcode::Add);
+        let v = add
+            .eval(&env(&[("uch", 255), ("sch", -128)]), &target)
+            .unwrap();
+        assert_eq!(v, Value::new(127, CDataTypes::Int, &target));
+        let v = add
+            .eval(&env(&[("uch", 300), ("sch", 130)]), &target)
+            .unwrap();
+        assert_eq!(v.val, 44 - 126);
+
+        // Unsigned arithmetic wraps.
+        let sub = BinarySymExpr::new(&u, &u, Opcode::Sub);
+        let one = Conc::new(1, CDataTypes::UnsignedInt);
+        let dec = BinarySymExpr::new(&sub, &one, Opcode::Sub);
+        let v = dec.eval(&env(&[("u", 7)]), &target).unwrap();
+        assert_eq!(
+            v,
+            Value::new(u32::MAX as i128, CDataTypes::UnsignedInt, &target)
+        );
+
+        // Signed int mixed with unsigned long is converted to unsigned long.
+        let ul = Sym::new("ul", "unsigned long");
+        let gt = BinarySymExpr::new(&s, &ul, Opcode::GT);
+        assert_eq!(
+            gt.eval(&env(&[("s", -1), ("ul", 5)]), &target).unwrap().val,
+            1
+        );
+    }
+
+    #[test]
+    fn test_evalShiftAndLogical() {
+        let target = TargetInfo::default();
+        let s = Sym::new("s", "int");
+        let c = Conc::new(32, CDataTypes::Int);
+        let shl = BinarySymExpr::new(&s, &c, Opcode::Shl);
+        assert_eq!(
+            shl.eval(&env(&[("s", 1)]), &target),
+            Err(EvalError::InvalidShift)
+        );
+
+        let zero = Conc::new(0, CDataTypes::Int);
+        let div = BinarySymExpr::new(&s, &zero, Opcode::Div);
+        let land = BinarySymExpr::new(&zero, &div, Opcode::LAnd);
+        assert_eq!(land.eval(&env(&[("s", 1)]), &target).unwrap().val, 0);
+
+        let sh = Sym::new("sh", "short");
+        let c3 = Conc::new(3, CDataTypes::Int);
+        let shr = BinarySymExpr::new(&sh, &c3, Opcode::Shr);
+        let v = shr.eval(&env(&[("sh", -16)]), &target).unwrap();
+        assert_eq!(v, Value::new(-2, CDataTypes::Int, &target));
+    }
+}
@@ -1,7 +1,16 @@
-//! Expr : An expression type. This may be extended for UnarySymExprs as well.
+//! Expr : Expression types, BinarySymExpr and UnarySymExpr.
+//!
+//! BinarySymExpr nests its operand types, so the shape of an expression is
+//! part of its type. Expr is the same tree with the shape only known at run
+//! time, for expressions which are generated, parsed or stored together.
 
 use crate::BinOp::Opcode;
+use crate::DataType::CDataTypes;
 use crate::Draw::Draw;
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::Transfer::getUnaryResultType;
+use crate::UnOp::UnaryOpcode;
 use std::fmt;
 use std::sync::Arc;
 
@@ -48,6 +57,18 @@ impl<T1, T2> BinarySymExpr<T1, T2> {
             Op,
         })
     }
+
+    pub fn getLHS(&self) -> &Arc<T1> {
+        &self.LHS
+    }
+
+    pub fn getRHS(&self) -> &Arc<T2> {
+        &self.RHS
+    }
+
+    pub fn getOpcode(&self) -> Opcode {
+        self.Op
+    }
 }
 
 impl<T1, T2> fmt::Display for BinarySymExpr<T1, T2>
@@ -70,10 +91,11 @@ where
     /// cases.
     fn dump(&self, tabl: Option<usize>) -> String {
         format!(
-            "{}{}\n{}",
+            "{}{ws}{}\n{}",
             self.LHS.dump(Some(tabl.unwrap_or(0) + 4)),
-            format!("{ws}{}", self.Op, ws = " ".repeat(tabl.unwrap_or(0))),
-            self.RHS.dump(Some(tabl.unwrap_or(0) + 4))
+            self.Op,
+            self.RHS.dump(Some(tabl.unwrap_or(0) + 4)),
+            ws = " ".repeat(tabl.unwrap_or(0)),
         )
     }
 
@@ -81,7 +103,7 @@ where
         // A BinarySymExpr is complex type. We need to distinguish between Sym
         // and BinarySymExpr<T> (where T ≠ Sym). We should consider case where
         // to put semicolon and comma.
-        format!("")
+        String::new()
     }
 
     type OutputType = BinarySymExpr<T1, T2>;
@@ -97,83 +119,424 @@ where
     }
 }
 
+/// A unary operator or an explicit cast applied to an expression, e.g. `-x`
+/// or `(unsigned char)x`.
+pub struct UnarySymExpr<T> {
+    Operand: Arc<T>,
+    Op: UnaryOpcode,
+}
+
+impl<T> UnarySymExpr<T> {
+    pub fn new(Operand: &Arc<T>, Op: UnaryOpcode) -> Arc<Self> {
+        Arc::new(Self {
+            Operand: Arc::clone(Operand),
+            Op,
+        })
+    }
+
+    pub fn getOperand(&self) -> &Arc<T> {
+        &self.Operand
+    }
+
+    pub fn getOpcode(&self) -> UnaryOpcode {
+        self.Op
+    }
+}
+
+impl<T> fmt::Display for UnarySymExpr<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}{})", self.Op, self.Operand)
+    }
+}
+
+impl<T> Draw for UnarySymExpr<T>
+where
+    T: Draw,
+{
+    fn dump(&self, tabl: Option<usize>) -> String {
+        format!(
+            "{ws}{}\n{}",
+            self.Op,
+            self.Operand.dump(Some(tabl.unwrap_or(0) + 4)),
+            ws = " ".repeat(tabl.unwrap_or(0)),
+        )
+    }
+
+    fn declare(&self) -> String {
+        String::new()
+    }
+
+    type OutputType = UnarySymExpr<T>;
+    fn iter(&self) -> (bool, &Self::OutputType) {
+        #[cfg(debug_assertions)]
+        println!("USE: {}", self);
+
+        let (isBase, _Operand) = T::iter(&self.Operand);
+        (isBase, self)
+    }
+}
+
+/// An expression tree of any shape.
+///
+/// # Examples
+///
+/// ```
+/// use symi::BinOp::Opcode;
+/// use symi::DataType::CDataTypes;
+/// use symi::Expr::{BinarySymExpr, Expr, ToExpr};
+/// use symi::Symbol::{Conc, Sym};
+/// use std::sync::Arc;
+///
+/// let x = Sym::new("x", "int");
+/// let mut conds: Vec<Arc<Expr>> = vec![];
+/// for val in 0..3 {
+///     let conc = Conc::new(val, CDataTypes::Int);
+///     conds.push(BinarySymExpr::new(&x, &conc, Opcode::NE).toExpr());
+/// }
+/// let all = Expr::binary(&conds[0], &conds[1], Opcode::LAnd);
+/// assert_eq!(all.to_string(), "((x != 0) && (x != 1))");
+/// ```
+#[derive(Clone)]
+pub enum Expr {
+    Sym(Arc<Sym>),
+    Conc(Arc<Conc>),
+    Binary(Arc<BinarySymExpr<Expr, Expr>>),
+    Unary(Arc<UnarySymExpr<Expr>>),
+}
+
+impl Expr {
+    pub fn binary(LHS: &Arc<Expr>, RHS: &Arc<Expr>, Op: Opcode) -> Arc<Expr> {
+        Arc::new(Expr::Binary(BinarySymExpr::new(LHS, RHS, Op)))
+    }
+
+    pub fn unary(Operand: &Arc<Expr>, Op: UnaryOpcode) -> Arc<Expr> {
+        Arc::new(Expr::Unary(UnarySymExpr::new(Operand, Op)))
+    }
+
+    /// C type of the expression on `target`.
+    pub fn getType(&self, target: &TargetInfo) -> CDataTypes {
+        match self {
+            Expr::Sym(sym) => sym.ty,
+            Expr::Conc(conc) => conc.ty,
+            Expr::Binary(bse) => bse.getOpcode().getResultType(
+                bse.getLHS().getType(target),
+                bse.getRHS().getType(target),
+                target,
+            ),
+            Expr::Unary(unary) => {
+                getUnaryResultType(unary.getOpcode(), unary.getOperand().getType(target))
+            }
+        }
+    }
+
+    /// Returns the BinarySymExpr if the expression is one, e.g. to be used as
+    /// the Conditional or Assertion of a Test.
+    pub fn asBinary(&self) -> Option<&Arc<BinarySymExpr<Expr, Expr>>> {
+        match self {
+            Expr::Binary(bse) => Some(bse),
+            _ => None,
+        }
+    }
+}
+
+/// Conversion of the generic expression types into an Expr.
+pub trait ToExpr {
+    fn toExpr(&self) -> Arc<Expr>;
+}
+
+impl ToExpr for Arc<Sym> {
+    fn toExpr(&self) -> Arc<Expr> {
+        Arc::new(Expr::Sym(Arc::clone(self)))
+    }
+}
+
+impl ToExpr for Arc<Conc> {
+    fn toExpr(&self) -> Arc<Expr> {
+        Arc::new(Expr::Conc(Arc::clone(self)))
+    }
+}
+
+impl ToExpr for Arc<Expr> {
+    fn toExpr(&self) -> Arc<Expr> {
+        Arc::clone(self)
+    }
+}
+
+impl<T1, T2> ToExpr for Arc<BinarySymExpr<T1, T2>>
+where
+    Arc<T1>: ToExpr,
+    Arc<T2>: ToExpr,
+{
+    fn toExpr(&self) -> Arc<Expr> {
+        Expr::binary(&self.LHS.toExpr(), &self.RHS.toExpr(), self.Op)
+    }
+}
+
+impl<T> ToExpr for Arc<UnarySymExpr<T>>
+where
+    Arc<T>: ToExpr,
+{
+    fn toExpr(&self) -> Arc<Expr> {
+        Expr::unary(&self.Operand.toExpr(), self.Op)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Sym(sym) => write!(f, "{}", sym),
+            Expr::Conc(conc) => write!(f, "{}", conc),
+            Expr::Binary(bse) => write!(f, "{}", bse),
+            Expr::Unary(unary) => write!(f, "{}", unary),
+        }
+    }
+}
+
+impl Draw for Expr {
+    fn dump(&self, tabl: Option<usize>) -> String {
+        match self {
+            Expr::Sym(sym) => sym.dump(tabl),
+            Expr::Conc(conc) => conc.dump(tabl),
+            Expr::Binary(bse) => bse.dump(tabl),
+            Expr::Unary(unary) => unary.dump(tabl),
+        }
+    }
+
+    fn declare(&self) -> String {
+        match self {
+            Expr::Sym(sym) => sym.declare(),
+            Expr::Conc(conc) => conc.declare(),
+            Expr::Binary(bse) => bse.declare(),
+            Expr::Unary(unary) => unary.declare(),
+        }
+    }
+
+    type OutputType = Expr;
+    fn iter(&self) -> (bool, &Self::OutputType) {
+        let isBase = match self {
+            Expr::Sym(sym) => sym.iter().0,
+            Expr::Conc(conc) => conc.iter().0,
+            Expr::Binary(bse) => bse.iter().0,
+            Expr::Unary(unary) => unary.iter().0,
+        };
+        (isBase, self)
+    }
+}
+
 /// A collection of BSE<T1, T2> joined by a single logical binary operator.
 ///
 /// # Examples
 ///
-/// ```text
+/// ```
+/// use symi::BinOp::Opcode;
+/// use symi::DataType::CDataTypes;
+/// use symi::Expr::{BinarySymExpr, ChainedBSE};
+/// use symi::Symbol::{Conc, Sym};
+///
+/// let s = Sym::new("s", "int");
+/// let c1 = BinarySymExpr::new(&s, &Conc::new(1, CDataTypes::Int), Opcode::GE);
+/// let c3 = BinarySymExpr::new(&s, &Conc::new(3, CDataTypes::Int), Opcode::LE);
+/// let c2 = BinarySymExpr::new(&s, &Conc::new(2, CDataTypes::Int), Opcode::NE);
 ///
+/// let chain = ChainedBSE::new(&[&c1, &c3, &c2], Opcode::LAnd);
+/// assert_eq!(chain.to_string(), "(((s >= 1) && (s <= 3)) && (s != 2))");
 /// ```
-pub struct ChainedBSE<'a, T1, T2> {
+pub struct ChainedBSE<T1, T2> {
     Op: Opcode,
-    BSEs: &'a [BinarySymExpr<T1, T2>],
+    BSEs: Vec<Arc<BinarySymExpr<T1, T2>>>,
 }
 
-impl<'a, T1: fmt::Display, T2: fmt::Display> ChainedBSE<'a, T1, T2> {
+impl<T1, T2> ChainedBSE<T1, T2> {
     // Variable number of BSEs all joined together by one binary operator.
-    pub fn new(BSEs: &'a [BinarySymExpr<T1, T2>], Op: Opcode) -> ChainedBSE<'a, T1, T2> {
-        // // Combine all BSEs in recursive manner.
-        // for idx in 0..BSEs.len() {
-        //     // Join RHS
-        //     let RecurSym = BinarySymExpr::new(BSEs[idx], BSEs[idx+1..], Op);
-        // }
-
-        ChainedBSE { Op, BSEs }
+    pub fn new(BSEs: &[&Arc<BinarySymExpr<T1, T2>>], Op: Opcode) -> ChainedBSE<T1, T2> {
+        ChainedBSE {
+            Op,
+            BSEs: BSEs.iter().map(|BSE| Arc::clone(BSE)).collect(),
+        }
     }
+}
 
+impl<T1, T2> ChainedBSE<T1, T2>
+where
+    Arc<T1>: ToExpr,
+    Arc<T2>: ToExpr,
+{
     // Join all conditionals in BSEs with Op.
     // [BSE(1 <= s), BSE(s <= 3)].join = BSE((1 <= s) && (s <= 3))
-    pub fn join(&self) {
-        // TODO: This requires RecursiveIteration over BinarySymExpr to generate
-        // something like:
-        //      (((BSE1 Op BSE2) Op BSE3) Op BSE4)
-        // from a vector of BSE<Sym,Sym>(s).
-        println!("Joining is happening attention");
-        for cond in self.BSEs {
-            print!("{} ", cond);
-        }
-        println!("\nJoining is done");
+    //
+    // The result is left-folded, (((BSE1 Op BSE2) Op BSE3) Op BSE4), or None if
+    // there is nothing to join.
+    pub fn join(&self) -> Option<Arc<BinarySymExpr<Expr, Expr>>> {
+        let (first, rest) = self.BSEs.split_first()?;
+        let joined = rest.iter().fold(first.toExpr(), |joined, BSE| {
+            Expr::binary(&joined, &BSE.toExpr(), self.Op)
+        });
+        joined.asBinary().cloned()
     }
 }
 
-impl<'a, T1, T2> fmt::Display for ChainedBSE<'a, T1, T2>
+impl<T1, T2> fmt::Display for ChainedBSE<T1, T2>
 where
-    T1: fmt::Display,
-    T2: fmt::Display,
+    Arc<T1>: ToExpr,
+    Arc<T2>: ToExpr,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for BSE in self.BSEs {
-            println!("{} {} ", BSE, self.Op);
+        match self.join() {
+            Some(joined) => write!(f, "{}", joined),
+            None => Ok(()),
         }
-        write!(f, "( {} )", self.Op)
     }
 }
 
-impl<'a, T1, T2> Draw for ChainedBSE<'a, T1, T2>
+impl<T1, T2> Draw for ChainedBSE<T1, T2>
 where
     T1: Draw,
     T2: Draw,
+    Arc<T1>: ToExpr,
+    Arc<T2>: ToExpr,
 {
     fn dump(&self, tabl: Option<usize>) -> String {
-        let mut _ret = String::new();
-
-        for BSE in self.BSEs {
-            BSE.dump(None);
-            // ret.push(!("{} {}", BSE.dump(Some(tabl.unwrap_or(0) + 4)), self.Op));
+        match self.join() {
+            Some(joined) => joined.dump(tabl),
+            None => String::new(),
         }
-
-        format!(
-            "{}\n",
-            format!("{ws}{}", self.Op, ws = " ".repeat(tabl.unwrap_or(0))),
-        )
     }
 
     fn declare(&self) -> String {
         String::new()
     }
 
-    type OutputType = BinarySymExpr<T1, T2>;
+    type OutputType = ChainedBSE<T1, T2>;
     fn iter(&self) -> (bool, &Self::OutputType) {
-        (true, &self.BSEs[0])
+        let isBase = self.BSEs.iter().all(|BSE| BSE.iter().0);
+        (isBase, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Eval::{Env, Eval};
+    use crate::Oracle::Expectation;
+    use crate::Range::Constrain;
+    use crate::Test::DynTest;
+
+    #[test]
+    fn test_Expr() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "int");
+        let y = Sym::new("y", "unsigned char");
+        let c10 = Conc::new(10, CDataTypes::Int);
+        let bse = BinarySymExpr::new(&x, &c10, Opcode::LT);
+        let nested = BinarySymExpr::new(&bse, &y, Opcode::LAnd);
+
+        let expr = nested.toExpr();
+        assert_eq!(expr.to_string(), nested.to_string());
+        assert_eq!(expr.dump(None), nested.dump(None));
+        assert_eq!(
+            expr.constraints(&target).to_string(),
+            "x: [-2147483648, 9], y: [1, 255]"
+        );
+
+        let mut env = Env::new();
+        env.insert("x".to_string(), 3);
+        env.insert("y".to_string(), 0);
+        assert!(!expr.eval(&env, &target).unwrap().isTrue());
+
+        // Built in a loop, which the generic types can not do.
+        let mut cond = x.toExpr();
+        for val in 1..4 {
+            let conc = Conc::new(val, CDataTypes::Int).toExpr();
+            cond = Expr::binary(&cond, &conc, Opcode::Add);
+        }
+        assert_eq!(cond.to_string(), "(((x + 1) + 2) + 3)");
+        assert!(cond.asBinary().is_some());
+        assert!(x.toExpr().asBinary().is_none());
+        assert_eq!(cond.getType(&target), CDataTypes::Int);
+        let two = Conc::new(2, CDataTypes::Double).toExpr();
+        let quot = Expr::binary(&y.toExpr(), &two, Opcode::Div);
+        assert_eq!(quot.getType(&target), CDataTypes::Double);
+
+        let gt = Expr::binary(&cond, &c10.toExpr(), Opcode::GT);
+        let test: Arc<DynTest> =
+            crate::Test::Test::new(expr.asBinary().unwrap(), gt.asBinary().unwrap(), &target);
+        assert!(test
+            .to_string()
+            .contains("clang_analyzer_eval(x + 1 + 2 + 3 > 10)"));
+    }
+
+    #[test]
+    fn test_UnarySymExpr() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "int");
+        let c0 = Conc::new(0, CDataTypes::Int);
+        let c120 = Conc::new(120, CDataTypes::Int);
+        let c130 = Conc::new(130, CDataTypes::Int);
+
+        // (int)(unsigned char)x < 0
+        let uch = UnarySymExpr::new(&x, UnaryOpcode::Cast(CDataTypes::UnsignedChar));
+        let int = UnarySymExpr::new(&uch, UnaryOpcode::Cast(CDataTypes::Int));
+        let neg = BinarySymExpr::new(&int, &c0, Opcode::LT);
+        assert_eq!(neg.to_string(), "(((int)((unsigned char)x)) < 0)");
+        assert_eq!(
+            neg.dump(None),
+            "    (int)\n        (unsigned char)\n            x\n<\n    0\n"
+        );
+        assert_eq!(neg.toExpr().to_string(), neg.to_string());
+
+        let ge = BinarySymExpr::new(&x, &c120, Opcode::GE);
+        let le = BinarySymExpr::new(&x, &c130, Opcode::LE);
+        let cond = BinarySymExpr::new(&ge, &le, Opcode::LAnd);
+        let test = crate::Test::Test::new(&cond, &neg, &target);
+        assert_eq!(test.getExpectation(), Some(Expectation::False));
+
+        // 128 and above turn negative as signed char.
+        let sch = UnarySymExpr::new(&x, UnaryOpcode::Cast(CDataTypes::SignedChar));
+        let neg = BinarySymExpr::new(&sch, &c0, Opcode::LT);
+        let test = crate::Test::Test::new(&cond, &neg, &target);
+        assert_eq!(test.getExpectation(), Some(Expectation::Unknown));
+
+        let lnot = UnarySymExpr::new(&x, UnaryOpcode::LNot);
+        assert_eq!(lnot.constraints(&target).to_string(), "x: [0, 0]");
+        let wide = UnarySymExpr::new(&x, UnaryOpcode::Cast(CDataTypes::Long));
+        assert_eq!(
+            wide.constraints(&target).to_string(),
+            "x: [-2147483648, -1] U [1, 2147483647]"
+        );
+    }
+
+    #[test]
+    fn test_ChainedBSE() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "int");
+        let y = Sym::new("y", "int");
+        let c1 = BinarySymExpr::new(&x, &Conc::new(1, CDataTypes::Int), Opcode::GE);
+        let c2 = BinarySymExpr::new(&x, &Conc::new(3, CDataTypes::Int), Opcode::LE);
+        let c3 = BinarySymExpr::new(&y, &Conc::new(5, CDataTypes::Int), Opcode::GE);
+        let c4 = BinarySymExpr::new(&y, &Conc::new(7, CDataTypes::Int), Opcode::LE);
+
+        let chain = ChainedBSE::new(&[&c1, &c2, &c3, &c4], Opcode::LAnd);
+        let joined = chain.join().unwrap();
+        assert_eq!(
+            joined.to_string(),
+            "((((x >= 1) && (x <= 3)) && (y >= 5)) && (y <= 7))"
+        );
+        assert_eq!(chain.dump(None), joined.dump(None));
+        assert_eq!(
+            joined.constraints(&target).to_string(),
+            "x: [1, 3], y: [5, 7]"
+        );
+
+        let lt = BinarySymExpr::new(&x, &y, Opcode::LT);
+        let test = crate::Test::Test::new(&joined, &lt, &target);
+        assert!(test.to_string().contains("expected-warning{{TRUE}}"));
+
+        assert_eq!(ChainedBSE::new(&[&c1], Opcode::LOr).to_string(), "(x >= 1)");
+        assert!(ChainedBSE::<Sym, Conc>::new(&[], Opcode::LOr)
+            .join()
+            .is_none());
     }
 }
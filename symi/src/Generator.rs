@@ -0,0 +1,471 @@
+//! Generator : Build random expressions of bounded depth over a set of
+//! symbols.
+//!
+//! This is the probabilistic model the Symbol module talks about. Each node of
+//! the tree is picked according to Weights: whether to nest another operation
+//! or stop at a leaf, which operator, whether a leaf is a symbol or a constant,
+//! and the types of constants and casts. Trees go deeper and mix types in ways
+//! the corner case strategies never do, which is what the constraint solver of
+//! the analyzer needs to be fuzzed with.
+//!
+//! The generator is driven by a seeded SplitMix64, so that the same seed, the
+//! same symbols and the same Weights always give the same expressions.
+//!
+//! # Examples
+//! ```
+//! use symi::Generator::{Generator, Weights};
+//! use symi::Symbol::Sym;
+//! use symi::Target::TargetInfo;
+//!
+//! let syms = [Sym::new("x", "int"), Sym::new("y", "unsigned char")];
+//! let target = TargetInfo::default();
+//! let mut gen = Generator::new(42, &syms, 4, Weights::default(), &target);
+//! let cond = gen.binary();
+//! assert_eq!(
+//!     cond.to_string(),
+//!     Generator::new(42, &syms, 4, Weights::default(), &target)
+//!         .binary()
+//!         .to_string()
+//! );
+//! ```
+
+use crate::BinOp::{Category, Opcode};
+use crate::DataType::CDataTypes;
+use crate::Expr::{BinarySymExpr, Expr, ToExpr};
+use crate::Parse::SymbolTable;
+use crate::Strategy::SplitMix64;
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::UnOp::UnaryOpcode;
+use std::sync::Arc;
+
+/// Relative weights of the choices of a Generator. A choice of weight 0 is
+/// never made.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weights {
+    /// Binary operators. Assignments are never generated, their LHS would have
+    /// to be a symbol.
+    pub binary: Vec<(Opcode, u32)>,
+    /// Unary operators other than casts.
+    pub unary: Vec<(UnaryOpcode, u32)>,
+    /// Casts, to a type picked from `types`.
+    pub cast: u32,
+    /// Types of constants and casts.
+    pub types: Vec<(CDataTypes, u32)>,
+    /// Operations, against leaves, below the maximum depth.
+    pub nest: u32,
+    pub leaf: u32,
+    /// Symbols, against constants, as leaves.
+    pub sym: u32,
+    pub conc: u32,
+}
+
+impl Default for Weights {
+    /// All integer types alike. Comparisons are the most common operators and
+    /// arithmetic the least, so that fewer expressions overflow.
+    fn default() -> Self {
+        use Opcode::*;
+        let binary = [
+            Mul, Div, Rem, Add, Sub, Shl, Shr, LT, GT, LE, GE, EQ, NE, And, Xor, Or, LAnd, LOr,
+        ]
+        .into_iter()
+        .map(|op| match op.getCategory() {
+            Category::Comparison => (op, 3),
+            Category::Bitwise | Category::Logical => (op, 2),
+            _ => (op, 1),
+        })
+        .collect();
+        let types = [
+            CDataTypes::Char,
+            CDataTypes::SignedChar,
+            CDataTypes::UnsignedChar,
+            CDataTypes::Short,
+            CDataTypes::UnsignedShort,
+            CDataTypes::Int,
+            CDataTypes::UnsignedInt,
+            CDataTypes::Long,
+            CDataTypes::UnsignedLong,
+            CDataTypes::LongLong,
+            CDataTypes::UnsignedLongLong,
+        ]
+        .into_iter()
+        .map(|ty| (ty, 1))
+        .collect();
+
+        Weights {
+            binary,
+            unary: vec![
+                (UnaryOpcode::Neg, 1),
+                (UnaryOpcode::Not, 1),
+                (UnaryOpcode::LNot, 1),
+            ],
+            cast: 1,
+            types,
+            nest: 3,
+            leaf: 1,
+            sym: 3,
+            conc: 2,
+        }
+    }
+}
+
+/// Sum of the weights of `choices`.
+fn total<T>(choices: &[(T, u32)]) -> u64 {
+    choices.iter().map(|(_, w)| *w as u64).sum()
+}
+
+/// Returns one of `choices`, with a chance proportional to its weight, or None
+/// if all weights are 0.
+fn pick<T: Copy>(rng: &mut SplitMix64, choices: &[(T, u32)]) -> Option<T> {
+    let total = total(choices);
+    if total == 0 {
+        return None;
+    }
+    let mut at = rng.next() % total;
+    for (choice, w) in choices {
+        if at < *w as u64 {
+            return Some(*choice);
+        }
+        at -= *w as u64;
+    }
+    unreachable!()
+}
+
+/// Random expressions over a set of symbols.
+pub struct Generator {
+    rng: SplitMix64,
+    symbols: Vec<Arc<Sym>>,
+    maxDepth: usize,
+    weights: Weights,
+    /// Target whose types bound the constants.
+    target: TargetInfo,
+}
+
+impl Generator {
+    /// Returns a generator of expressions over `symbols`, with at most
+    /// `maxDepth` operations from the root to any leaf, and constants of the
+    /// types of `target`.
+    pub fn new(
+        seed: u64,
+        symbols: &[Arc<Sym>],
+        maxDepth: usize,
+        weights: Weights,
+        target: &TargetInfo,
+    ) -> Generator {
+        let mut weights = weights;
+        weights.binary.retain(|(op, _)| *op != Opcode::Assign);
+        Generator {
+            rng: SplitMix64(seed),
+            symbols: symbols.to_vec(),
+            maxDepth,
+            weights,
+            target: target.clone(),
+        }
+    }
+
+    /// Returns a generator over the symbols of `table`, taken in order of their
+    /// names so that the order of the table does not matter.
+    pub fn fromTable(
+        seed: u64,
+        table: &SymbolTable,
+        maxDepth: usize,
+        weights: Weights,
+        target: &TargetInfo,
+    ) -> Generator {
+        let mut names: Vec<&String> = table.keys().collect();
+        names.sort();
+        let symbols: Vec<Arc<Sym>> = names
+            .into_iter()
+            .map(|name| {
+                Arc::new(Sym {
+                    name: name.clone(),
+                    ty: table[name],
+                    range: None,
+                })
+            })
+            .collect();
+        Generator::new(seed, &symbols, maxDepth, weights, target)
+    }
+
+    /// Returns a random expression.
+    pub fn expr(&mut self) -> Arc<Expr> {
+        self.node(self.maxDepth)
+    }
+
+    /// Returns a random binary operation, e.g. to be the Conditional or the
+    /// Assertion of a Test. Its operands are at most `maxDepth - 1` deep.
+    pub fn binary(&mut self) -> Arc<BinarySymExpr<Expr, Expr>> {
+        let op = pick(&mut self.rng, &self.weights.binary).unwrap_or(Opcode::EQ);
+        let depth = self.maxDepth.saturating_sub(1);
+        let (lhs, rhs) = (self.node(depth), self.node(depth));
+        let op = self.binaryOp(op, &lhs, &rhs);
+        let (lhs, rhs) = self.operands(lhs, rhs, op);
+        BinarySymExpr::new(&lhs, &rhs, op)
+    }
+
+    /// An expression at most `depth` deep.
+    fn node(&mut self, depth: usize) -> Arc<Expr> {
+        let nest = depth > 0
+            && pick(
+                &mut self.rng,
+                &[(true, self.weights.nest), (false, self.weights.leaf)],
+            )
+            .unwrap_or(false);
+        if !nest {
+            return self.leaf();
+        }
+
+        let unary = total(&self.weights.unary).min(u32::MAX as u64) as u32;
+        let binary = total(&self.weights.binary).min(u32::MAX as u64) as u32;
+        // There is nothing to cast to without types.
+        let cast = if total(&self.weights.types) == 0 {
+            0
+        } else {
+            self.weights.cast
+        };
+        match pick(&mut self.rng, &[(0, binary), (1, unary), (2, cast)]) {
+            Some(0) => {
+                let op = pick(&mut self.rng, &self.weights.binary).unwrap();
+                let (lhs, rhs) = (self.node(depth - 1), self.node(depth - 1));
+                let op = self.binaryOp(op, &lhs, &rhs);
+                let (lhs, rhs) = self.operands(lhs, rhs, op);
+                Expr::binary(&lhs, &rhs, op)
+            }
+            Some(1) => {
+                let op = pick(&mut self.rng, &self.weights.unary).unwrap();
+                let operand = self.node(depth - 1);
+                let op = self.unaryOp(op, &operand);
+                Expr::unary(&operand, op)
+            }
+            Some(_) => {
+                let ty = pick(&mut self.rng, &self.weights.types).unwrap();
+                Expr::unary(&self.node(depth - 1), UnaryOpcode::Cast(ty))
+            }
+            None => self.leaf(),
+        }
+    }
+
+    /// `op`, or if it needs integers and one of the operands is floating,
+    /// another binary operator picked from the ones which do not.
+    fn binaryOp(&mut self, op: Opcode, lhs: &Expr, rhs: &Expr) -> Opcode {
+        let floating = [lhs, rhs]
+            .iter()
+            .any(|expr| !expr.getType(&self.target).isInteger());
+        if !floating || !op.needsIntegers() {
+            return op;
+        }
+
+        let valid: Vec<(Opcode, u32)> = self
+            .weights
+            .binary
+            .iter()
+            .filter(|(op, _)| !op.needsIntegers())
+            .copied()
+            .collect();
+        pick(&mut self.rng, &valid).unwrap_or(Opcode::EQ)
+    }
+
+    /// `op`, or if it is `~` and the operand is floating, another unary
+    /// operator picked from the others.
+    fn unaryOp(&mut self, op: UnaryOpcode, operand: &Expr) -> UnaryOpcode {
+        if op != UnaryOpcode::Not || operand.getType(&self.target).isInteger() {
+            return op;
+        }
+
+        let valid: Vec<(UnaryOpcode, u32)> = self
+            .weights
+            .unary
+            .iter()
+            .filter(|(op, _)| *op != UnaryOpcode::Not)
+            .copied()
+            .collect();
+        pick(&mut self.rng, &valid).unwrap_or(UnaryOpcode::LNot)
+    }
+
+    /// The operands of `op`, with the constant ones clang warns about replaced
+    /// by 0 or 1: those of `&&` and `||`, and those compared to a truth value.
+    /// `-verify` rejects the warnings as unexpected.
+    fn operands(&mut self, lhs: Arc<Expr>, rhs: Arc<Expr>, op: Opcode) -> (Arc<Expr>, Arc<Expr>) {
+        let (lhsTruth, rhsTruth) = match op.getCategory() {
+            Category::Logical => (true, true),
+            Category::Comparison => (isTruthValue(&rhs), isTruthValue(&lhs)),
+            _ => (false, false),
+        };
+        let mut truth = |expr: Arc<Expr>, clamp: bool| {
+            let isBool =
+                matches!(expr.as_ref(), Expr::Conc(conc) if conc.val == 0 || conc.val == 1);
+            if clamp && !isBool && isConstant(&expr) {
+                Conc::new((self.rng.next() % 2) as i128, CDataTypes::Int).toExpr()
+            } else {
+                expr
+            }
+        };
+        let lhs = truth(lhs, lhsTruth);
+        (lhs, truth(rhs, rhsTruth))
+    }
+
+    /// A symbol or a constant. Constants are often limits of their type or
+    /// values next to 0, where folding goes wrong the most. Values next to 0
+    /// are the most common, as they overflow the least.
+    fn leaf(&mut self) -> Arc<Expr> {
+        let sym = if self.symbols.is_empty() {
+            0
+        } else {
+            self.weights.sym
+        };
+        let choices = [(true, sym), (false, self.weights.conc)];
+        if pick(&mut self.rng, &choices).unwrap_or(!self.symbols.is_empty()) {
+            let at = self.rng.next() % self.symbols.len() as u64;
+            return self.symbols[at as usize].toExpr();
+        }
+
+        let ty = pick(&mut self.rng, &self.weights.types).unwrap_or(CDataTypes::Int);
+        let (min, max) = (ty.getMin(&self.target), ty.getMax(&self.target));
+        let val = match self.rng.next() % 4 {
+            0 => [min, max, min + 1, max - 1][(self.rng.next() % 4) as usize],
+            1 | 2 => (self.rng.next() % 17) as i128 - 8,
+            _ => {
+                let span = (max - min + 1) as u128;
+                let bits = ((self.rng.next() as u128) << 64) | self.rng.next() as u128;
+                min + (bits % span) as i128
+            }
+        };
+        // Values next to 0 wrap around to the largest ones of unsigned types.
+        Conc::wrapping(val, ty, &self.target).toExpr()
+    }
+}
+
+/// Whether `expr` has no symbols, i.e. is a constant expression.
+fn isConstant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Sym(_) => false,
+        Expr::Conc(_) => true,
+        Expr::Binary(bse) => isConstant(bse.getLHS()) && isConstant(bse.getRHS()),
+        Expr::Unary(unary) => isConstant(unary.getOperand()),
+    }
+}
+
+/// Whether `expr` is a comparison, a logical operation or a negation, whose
+/// values are 0 and 1.
+fn isTruthValue(expr: &Expr) -> bool {
+    match expr {
+        Expr::Binary(bse) => matches!(
+            bse.getOpcode().getCategory(),
+            Category::Comparison | Category::Logical
+        ),
+        Expr::Unary(unary) => unary.getOpcode() == UnaryOpcode::LNot,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::Fixture;
+
+    fn depth(expr: &Expr) -> usize {
+        match expr {
+            Expr::Sym(_) | Expr::Conc(_) => 0,
+            Expr::Unary(unary) => 1 + depth(unary.getOperand()),
+            Expr::Binary(bse) => 1 + depth(bse.getLHS()).max(depth(bse.getRHS())),
+        }
+    }
+
+    #[test]
+    fn test_Generator() {
+        let fixture = Fixture::new(&[
+            ("x", CDataTypes::Int),
+            ("y", CDataTypes::UnsignedLong),
+            ("z", CDataTypes::SignedChar),
+        ]);
+        let target = &fixture.target;
+        let exprs = |seed: u64, weights: Weights| {
+            let mut gen = Generator::fromTable(seed, &fixture.symbols, 5, weights, target);
+            (0..50).map(|_| gen.expr()).collect::<Vec<_>>()
+        };
+        let print = |exprs: &[Arc<Expr>]| exprs.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+
+        let first = exprs(7, Weights::default());
+        assert_eq!(print(&first), print(&exprs(7, Weights::default())));
+        assert_ne!(print(&first), print(&exprs(8, Weights::default())));
+        assert!(first.iter().all(|e| depth(e) <= 5));
+        assert!(first.iter().any(|e| depth(e) == 5));
+
+        // Only additions of symbols, as deep as allowed.
+        let weights = Weights {
+            binary: vec![(Opcode::Add, 1), (Opcode::Assign, 5)],
+            unary: vec![],
+            cast: 0,
+            leaf: 0,
+            conc: 0,
+            ..Weights::default()
+        };
+        for expr in exprs(7, weights) {
+            assert_eq!(depth(&expr), 5);
+            let text = expr.to_string();
+            assert!(!text.contains(|c: char| c.is_ascii_digit()), "{text}");
+            assert_eq!(text.matches(" + ").count(), 31);
+        }
+
+        // No constants but 0 and 1 as truth values.
+        fn warned(expr: &Expr) -> bool {
+            let isBool = |e: &Expr| matches!(e, Expr::Conc(c) if c.val == 0 || c.val == 1);
+            let clamped = |e: &Expr| isBool(e) || !isConstant(e);
+            match expr {
+                Expr::Binary(bse) => {
+                    let (lhs, rhs) = (bse.getLHS().as_ref(), bse.getRHS().as_ref());
+                    let bad = match bse.getOpcode().getCategory() {
+                        Category::Logical => !clamped(lhs) || !clamped(rhs),
+                        Category::Comparison => {
+                            (isTruthValue(lhs) && !clamped(rhs))
+                                || (isTruthValue(rhs) && !clamped(lhs))
+                        }
+                        _ => false,
+                    };
+                    bad || warned(lhs) || warned(rhs)
+                }
+                Expr::Unary(unary) => warned(unary.getOperand()),
+                _ => false,
+            }
+        }
+        let weights = Weights {
+            conc: 5,
+            ..Weights::default()
+        };
+        let all = exprs(3, weights);
+        assert!(all.iter().any(|e| e.to_string().contains(" && ")));
+        assert!(!all.iter().any(|e| warned(e)));
+
+        // Operators which need integers only get integers.
+        fn typed(expr: &Expr, target: &TargetInfo) -> bool {
+            let isInteger = |e: &Expr| e.getType(target).isInteger();
+            match expr {
+                Expr::Binary(bse) => {
+                    let (lhs, rhs) = (bse.getLHS().as_ref(), bse.getRHS().as_ref());
+                    (!bse.getOpcode().needsIntegers() || (isInteger(lhs) && isInteger(rhs)))
+                        && typed(lhs, target)
+                        && typed(rhs, target)
+                }
+                Expr::Unary(unary) => {
+                    let operand = unary.getOperand().as_ref();
+                    (unary.getOpcode() != UnaryOpcode::Not || isInteger(operand))
+                        && typed(operand, target)
+                }
+                _ => true,
+            }
+        }
+        let floats = Fixture::new(&[("d", CDataTypes::Double), ("x", CDataTypes::Int)]);
+        let weights = Weights {
+            types: vec![(CDataTypes::Float, 1), (CDataTypes::Int, 1)],
+            ..Weights::default()
+        };
+        let mut gen = Generator::fromTable(5, &floats.symbols, 5, weights, target);
+        let all: Vec<_> = (0..50).map(|_| gen.expr()).collect();
+        assert!(all.iter().any(|e| e.to_string().contains(".0F")));
+        assert!(all.iter().all(|e| typed(e, target)));
+
+        let mut gen = Generator::new(1, &[], 0, Weights::default(), target);
+        assert!(matches!(gen.expr().as_ref(), Expr::Conc(_)));
+        assert_eq!(depth(&Expr::Binary(gen.binary())), 1);
+    }
+}
@@ -0,0 +1,533 @@
+//! Oracle : Work out what the analyzer is expected to say about an assertion.
+//!
+//! For a test case `if (Conditional) { clang_analyzer_eval(Assertion); }` the
+//! analyzer reports TRUE if the assertion holds on every path through the
+//! conditional, FALSE if it never holds, and UNKNOWN otherwise. The oracle
+//! computes the same answer by evaluating both expressions over a set of
+//! candidate assignments of their symbols. When those are not all of the
+//! assignments, a TRUE or FALSE answer must also be proven by evaluating the
+//! assertion over the ranges of the conditional, see Transfer. Otherwise the
+//! oracle cannot tell, and no annotation would be right.
+//!
+//! A conditional which can never be true makes the body of the test dead code,
+//! which checks nothing. The oracle also tells whether that is the case.
+//!
+//! Symbols of floating types are only sampled at integer values, and their
+//! ranges are not derived from the conditional. An answer involving them is
+//! either UNKNOWN, seen on actual assignments, or Undecided. So is an answer
+//! on assignments Eval does not model.
+
+use crate::DataType::CDataTypes;
+use crate::Eval::{Env, EvalError, Leaf};
+use crate::Range::{Constrain, Constraints, RangeSet};
+use crate::Target::TargetInfo;
+use crate::Transfer::EvalRange;
+use std::fmt;
+
+/// Upper bound on the number of assignments the oracle is allowed to try.
+const MAX_ASSIGNMENTS: usize = 1 << 20;
+
+/// Result of `clang_analyzer_eval` expected inside `-verify` annotations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Expectation {
+    True,
+    False,
+    Unknown,
+}
+
+/// What the oracle found out about an assertion.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Verdict {
+    /// The analyzer has to report this result.
+    Expected(Expectation),
+    /// No assignment satisfying the Conditional was found, the assertion is
+    /// dead code if the Conditional is infeasible.
+    Unreached,
+    /// The assignments tried all agree, but they are not all of them and the
+    /// ranges of the Conditional do not prove the others agree too.
+    Undecided,
+}
+
+impl Verdict {
+    pub fn getExpectation(&self) -> Option<Expectation> {
+        match self {
+            Verdict::Expected(expected) => Some(*expected),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the Conditional of a test can be true, i.e. its body is reachable.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Feasibility {
+    Feasible,
+    Infeasible,
+    /// No satisfying assignment was found, but not all of them were tried.
+    Unknown,
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::True => write!(f, "TRUE"),
+            Expectation::False => write!(f, "FALSE"),
+            Expectation::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+/// Returns the expected result of `Assertion` under every assignment which
+/// satisfies `Conditional`.
+///
+/// The values of each symbol are bounded by the ranges derived from the
+/// Conditional. If all their combinations can be tried, the answer is exact.
+/// Otherwise an assertion seen both true and false is UNKNOWN all the same,
+/// but one seen only true or only false is Undecided, unless evaluating it over
+/// the ranges shows it cannot be anything else.
+pub fn expect<C, A>(Conditional: &C, Assertion: &A, target: &TargetInfo) -> Verdict
+where
+    C: Constrain,
+    A: EvalRange,
+{
+    let ranges = Conditional.constraints(target);
+    let mut leaves = vec![];
+    Conditional.leaves(&mut leaves, target);
+    Assertion.leaves(&mut leaves, target);
+    let (syms, concs) = symbols(leaves);
+    let Some((domains, exact)) = domains(&syms, &concs, &ranges, target) else {
+        return Verdict::Unreached;
+    };
+
+    let (mut seenTrue, mut seenFalse, mut unmodeled) = (false, false, false);
+    anyAssignment(&syms, &domains, |env| {
+        // Assignments on which either side has undefined behavior are not
+        // feasible paths for the analyzer.
+        match Conditional.eval(env, target) {
+            Ok(cond) if cond.isTrue() => match Assertion.eval(env, target) {
+                Ok(val) if val.isTrue() => seenTrue = true,
+                Ok(_) => seenFalse = true,
+                Err(EvalError::Unsupported(_)) => unmodeled = true,
+                Err(_) => {}
+            },
+            Err(EvalError::Unsupported(_)) => unmodeled = true,
+            _ => {}
+        }
+        seenTrue && seenFalse
+    });
+    let exact = exact && !unmodeled;
+
+    let proven = |expected: Expectation| {
+        let values = Assertion.evalRange(&ranges, target).range;
+        let holds = match expected {
+            Expectation::True => !values.contains(0),
+            _ => values.isEmpty() || values == RangeSet::point(0),
+        };
+        if exact || holds {
+            Verdict::Expected(expected)
+        } else {
+            Verdict::Undecided
+        }
+    };
+    match (seenTrue, seenFalse) {
+        (true, true) => Verdict::Expected(Expectation::Unknown),
+        (true, false) => proven(Expectation::True),
+        (false, true) => proven(Expectation::False),
+        (false, false) if unmodeled => Verdict::Undecided,
+        (false, false) => Verdict::Unreached,
+    }
+}
+
+/// Returns whether `expect` tries every assignment satisfying `Conditional`,
+/// so that its answer rests neither on samples nor on ranges.
+pub fn isExact<C, A>(Conditional: &C, Assertion: &A, target: &TargetInfo) -> bool
+where
+    C: Constrain,
+    A: EvalRange,
+{
+    let ranges = Conditional.constraints(target);
+    let mut leaves = vec![];
+    Conditional.leaves(&mut leaves, target);
+    Assertion.leaves(&mut leaves, target);
+    let (syms, concs) = symbols(leaves);
+    !matches!(domains(&syms, &concs, &ranges, target), Some((_, false)))
+}
+
+/// Returns whether `Conditional` can be true at all.
+///
+/// The ranges derived from the conditional bound the values of each symbol.
+/// If they allow at most MAX_ASSIGNMENTS combinations, all of them are tried
+/// and the answer is exact. That is the case for two 8 bit symbols, a single
+/// 16 bit one, and narrow ranges of wider types. Otherwise the conditional is
+/// either infeasible by its ranges, or feasible if one of the values the
+/// oracle samples satisfies it, or unknown.
+pub fn feasibility<C>(Conditional: &C, target: &TargetInfo) -> Feasibility
+where
+    C: Constrain,
+{
+    let ranges = Conditional.constraints(target);
+    let mut leaves = vec![];
+    Conditional.leaves(&mut leaves, target);
+    let (syms, concs) = symbols(leaves);
+    let Some((domains, exact)) = domains(&syms, &concs, &ranges, target) else {
+        return Feasibility::Infeasible;
+    };
+
+    let mut unmodeled = false;
+    let witness = anyAssignment(&syms, &domains, |env| match Conditional.eval(env, target) {
+        Ok(cond) => cond.isTrue(),
+        Err(err) => {
+            unmodeled |= matches!(err, EvalError::Unsupported(_));
+            false
+        }
+    });
+    let exact = exact && !unmodeled;
+    match (witness, exact) {
+        (true, _) => Feasibility::Feasible,
+        (false, true) => Feasibility::Infeasible,
+        (false, false) => Feasibility::Unknown,
+    }
+}
+
+/// Values to try for each of `syms` under `ranges`, and whether they are all
+/// the values the ranges allow. None if the ranges allow no value at all.
+///
+/// Unless all combinations can be tried, each symbol is sampled at the ends of
+/// its ranges, at the limits of its type and around every constant in `concs`,
+/// which is where the outcome of comparisons against those constants changes.
+/// Symbols of the narrowest ranges still take all of their values, as long as
+/// the number of assignments stays within MAX_ASSIGNMENTS.
+fn domains(
+    syms: &[(String, CDataTypes)],
+    concs: &[i128],
+    ranges: &Constraints,
+    target: &TargetInfo,
+) -> Option<(Vec<Vec<i128>>, bool)> {
+    if !ranges.isFeasible() {
+        return None;
+    }
+
+    let bounds: Vec<RangeSet> = syms
+        .iter()
+        .map(|(name, ty)| match ranges.get(name) {
+            Some(range) => range.clone(),
+            // Floating values as large as Eval computes with.
+            None if !ty.isInteger() => RangeSet::new(-(1 << 126), 1 << 126),
+            None => RangeSet::full(*ty, target),
+        })
+        .collect();
+    if bounds.iter().any(|range| range.isEmpty()) {
+        return None;
+    }
+
+    let all = |range: &RangeSet| -> Vec<i128> {
+        range
+            .getRanges()
+            .iter()
+            .flat_map(|(lo, hi)| *lo..=*hi)
+            .collect()
+    };
+    // Floating symbols also take values between the integers.
+    let exact = syms.iter().all(|(_, ty)| ty.isInteger())
+        && bounds
+            .iter()
+            .try_fold(1u128, |total, range| total.checked_mul(range.size()))
+            .is_some_and(|total| total <= MAX_ASSIGNMENTS as u128);
+    if exact {
+        return Some((bounds.iter().map(all).collect(), true));
+    }
+
+    let mut domains: Vec<Vec<i128>> = syms
+        .iter()
+        .zip(bounds.iter())
+        .map(|((_, ty), range)| {
+            let mut vals: Vec<i128> = candidates(*ty, concs, target)
+                .into_iter()
+                .filter(|val| range.contains(*val))
+                .collect();
+            for (lo, hi) in range.getRanges() {
+                vals.extend(
+                    [*lo, *lo + 1, *hi - 1, *hi]
+                        .iter()
+                        .filter(|v| range.contains(**v)),
+                );
+            }
+            vals.sort_unstable();
+            vals.dedup();
+            vals
+        })
+        .collect();
+
+    let mut total: u128 = domains.iter().map(|vals| vals.len() as u128).product();
+    let mut narrowest: Vec<usize> = (0..bounds.len()).collect();
+    narrowest.sort_by_key(|i| bounds[*i].size());
+    for i in narrowest {
+        let widened = (total / domains[i].len() as u128).saturating_mul(bounds[i].size());
+        if widened <= MAX_ASSIGNMENTS as u128 {
+            domains[i] = all(&bounds[i]);
+            total = widened;
+        }
+    }
+    Some((domains, false))
+}
+
+/// Symbols, each listed once, and values of the constants among `leaves`.
+fn symbols(leaves: Vec<Leaf<'_>>) -> (Vec<(String, CDataTypes)>, Vec<i128>) {
+    let mut syms: Vec<(String, CDataTypes)> = vec![];
+    let mut concs: Vec<i128> = vec![];
+    for leaf in leaves {
+        match leaf {
+            Leaf::Sym(sym) if !syms.iter().any(|(name, _)| name == &sym.name) => {
+                syms.push((sym.name.clone(), sym.ty));
+            }
+            Leaf::Conc(val) => concs.push(val.val),
+            _ => {}
+        }
+    }
+    (syms, concs)
+}
+
+/// Calls `f` on every assignment of `syms` to values of their `domains`, until
+/// it returns true. Returns whether it did.
+fn anyAssignment<F>(syms: &[(String, CDataTypes)], domains: &[Vec<i128>], mut f: F) -> bool
+where
+    F: FnMut(&Env) -> bool,
+{
+    if domains.iter().any(|domain| domain.is_empty()) {
+        return false;
+    }
+
+    let mut env = Env::new();
+    let mut idx = vec![0; syms.len()];
+    loop {
+        for (i, (name, _)) in syms.iter().enumerate() {
+            env.insert(name.clone(), domains[i][idx[i]]);
+        }
+        if f(&env) {
+            return true;
+        }
+
+        // Move to the next assignment, odometer style.
+        let mut pos = 0;
+        while pos < idx.len() {
+            idx[pos] += 1;
+            if idx[pos] < domains[pos].len() {
+                break;
+            }
+            idx[pos] = 0;
+            pos += 1;
+        }
+        if pos == idx.len() {
+            return false;
+        }
+    }
+}
+
+/// Values of type `ty` worth trying: its limits, its middle, values around 0
+/// and around each of `concs`.
+fn candidates(ty: CDataTypes, concs: &[i128], target: &TargetInfo) -> Vec<i128> {
+    let (T_MIN, T_MAX) = (ty.getMin(target), ty.getMax(target));
+    let T_MID = T_MIN + (T_MAX - T_MIN) / 2;
+    let mut vals = vec![
+        T_MIN,
+        T_MIN + 1,
+        T_MID - 1,
+        T_MID,
+        T_MID + 1,
+        T_MAX - 1,
+        T_MAX,
+        -1,
+        0,
+        1,
+    ];
+    for conc in concs {
+        vals.extend((conc - 2)..=(conc + 2));
+    }
+
+    let mut vals: Vec<i128> = vals.into_iter().map(|val| ty.wrap(val, target)).collect();
+    vals.sort_unstable();
+    vals.dedup();
+    vals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BinOp::Opcode;
+    use crate::Expr::{BinarySymExpr, UnarySymExpr};
+    use crate::Symbol::{Conc, Sym};
+    use crate::UnOp::UnaryOpcode;
+
+    #[test]
+    fn test_expectNarrow() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "unsigned char");
+        let y = Sym::new("y", "unsigned char");
+        let c10 = Conc::new(10, CDataTypes::Int);
+        let c20 = Conc::new(20, CDataTypes::Int);
+        let xl = BinarySymExpr::new(&x, &c10, Opcode::LT);
+        let yg = BinarySymExpr::new(&y, &c20, Opcode::GT);
+        let cond = BinarySymExpr::new(&xl, &yg, Opcode::LAnd);
+
+        let ne = BinarySymExpr::new(&x, &y, Opcode::NE);
+        let gt = BinarySymExpr::new(&x, &y, Opcode::GT);
+        let sum = BinarySymExpr::new(&x, &y, Opcode::Add);
+        let big = BinarySymExpr::new(&sum, &c20, Opcode::GT);
+        assert_eq!(
+            expect(cond.as_ref(), ne.as_ref(), &target),
+            Verdict::Expected(Expectation::True)
+        );
+        assert_eq!(
+            expect(cond.as_ref(), gt.as_ref(), &target),
+            Verdict::Expected(Expectation::False)
+        );
+        assert_eq!(
+            expect(cond.as_ref(), big.as_ref(), &target),
+            Verdict::Expected(Expectation::True)
+        );
+
+        let c30 = Conc::new(30, CDataTypes::Int);
+        let sub = BinarySymExpr::new(&sum, &c30, Opcode::LT);
+        assert_eq!(
+            expect(cond.as_ref(), sub.as_ref(), &target),
+            Verdict::Expected(Expectation::Unknown)
+        );
+    }
+
+    #[test]
+    fn test_feasibility() {
+        let target = TargetInfo::default();
+        let int = CDataTypes::Int;
+        let uch1 = Sym::new("uch1", "unsigned char");
+        let uch2 = Sym::new("uch2", "unsigned char");
+        let le = BinarySymExpr::new(&uch1, &Conc::new(252, int), Opcode::LE);
+        let ge = BinarySymExpr::new(&uch2, &Conc::new(255 + 3, int), Opcode::GE);
+        let cond = BinarySymExpr::new(&le, &ge, Opcode::LAnd);
+        assert_eq!(feasibility(cond.as_ref(), &target), Feasibility::Infeasible);
+
+        // The ranges allow it, trying all values does not.
+        let sum = BinarySymExpr::new(&uch1, &uch2, Opcode::Add);
+        let eq = BinarySymExpr::new(&sum, &Conc::new(3, int), Opcode::EQ);
+        let gt = BinarySymExpr::new(&uch2, &Conc::new(10, int), Opcode::GT);
+        let cond = BinarySymExpr::new(&eq, &gt, Opcode::LAnd);
+        assert_eq!(feasibility(cond.as_ref(), &target), Feasibility::Infeasible);
+        assert_eq!(feasibility(eq.as_ref(), &target), Feasibility::Feasible);
+
+        let x = Sym::new("x", "int");
+        let y = Sym::new("y", "int");
+        let lo = BinarySymExpr::new(&x, &Conc::new(100, int), Opcode::GT);
+        let hi = BinarySymExpr::new(&x, &Conc::new(2000000, int), Opcode::LT);
+        let cond = BinarySymExpr::new(&lo, &hi, Opcode::LAnd);
+        assert_eq!(feasibility(cond.as_ref(), &target), Feasibility::Feasible);
+
+        let mul = BinarySymExpr::new(&x, &y, Opcode::Mul);
+        let eq = BinarySymExpr::new(&mul, &Conc::new(7, int), Opcode::EQ);
+        let cond = BinarySymExpr::new(&eq, &lo, Opcode::LAnd);
+        assert_eq!(feasibility(cond.as_ref(), &target), Feasibility::Unknown);
+    }
+
+    #[test]
+    fn test_expectWide() {
+        let target = TargetInfo::default();
+        let u1 = Sym::new("u1", "unsigned int");
+        let u2 = Sym::new("u2", "unsigned int");
+        let c30 = Conc::new(30, CDataTypes::Int);
+        let c50 = Conc::new(50, CDataTypes::Int);
+        let c1 = BinarySymExpr::new(&u1, &c30, Opcode::LE);
+        let c2 = BinarySymExpr::new(&u2, &c50, Opcode::LE);
+        let cond = BinarySymExpr::new(&c1, &c2, Opcode::LAnd);
+
+        let rem = BinarySymExpr::new(&u1, &u2, Opcode::Rem);
+        let lt = BinarySymExpr::new(&rem, &c50, Opcode::LT);
+        assert_eq!(
+            expect(cond.as_ref(), lt.as_ref(), &target),
+            Verdict::Expected(Expectation::True)
+        );
+
+        // -1 converts to UINT_MAX, nothing is greater than it.
+        let m1 = Conc::new(-1, CDataTypes::Int);
+        let never = BinarySymExpr::new(&u1, &m1, Opcode::GT);
+        assert_eq!(
+            expect(never.as_ref(), lt.as_ref(), &target),
+            Verdict::Unreached
+        );
+    }
+
+    #[test]
+    fn test_expectUndecided() {
+        let target = TargetInfo::default();
+        let int = CDataTypes::Int;
+        let x = Sym::new("x", "int");
+        let y = Sym::new("y", "int");
+        let gt = BinarySymExpr::new(&x, &Conc::new(0, int), Opcode::GT);
+        let lt = BinarySymExpr::new(&x, &Conc::new(100, int), Opcode::LT);
+        let cond = BinarySymExpr::new(&gt, &lt, Opcode::LAnd);
+
+        // All of x in (0, 100) are tried, x = 7 among them.
+        let square = BinarySymExpr::new(&x, &x, Opcode::Mul);
+        let ne = BinarySymExpr::new(&square, &Conc::new(49, int), Opcode::NE);
+        assert_eq!(
+            expect(cond.as_ref(), ne.as_ref(), &target),
+            Verdict::Expected(Expectation::Unknown)
+        );
+
+        // No sampled values of x and y multiply to 11 * 13, which proves
+        // nothing.
+        let xgt = BinarySymExpr::new(&x, &Conc::new(1, int), Opcode::GT);
+        let ygt = BinarySymExpr::new(&y, &Conc::new(1, int), Opcode::GT);
+        let both = BinarySymExpr::new(&xgt, &ygt, Opcode::LAnd);
+        let mul = BinarySymExpr::new(&x, &y, Opcode::Mul);
+        let ne = BinarySymExpr::new(&mul, &Conc::new(143, int), Opcode::NE);
+        assert_eq!(
+            expect(both.as_ref(), ne.as_ref(), &target),
+            Verdict::Undecided
+        );
+
+        // Too many values of x to try them all, but its range proves it.
+        let wide = BinarySymExpr::new(&x, &Conc::new(2000000, int), Opcode::LT);
+        let cond = BinarySymExpr::new(&gt, &wide, Opcode::LAnd);
+        let sum = BinarySymExpr::new(&x, &Conc::new(1, int), Opcode::Add);
+        let pos = BinarySymExpr::new(&sum, &Conc::new(1, int), Opcode::GT);
+        assert_eq!(
+            expect(cond.as_ref(), pos.as_ref(), &target),
+            Verdict::Expected(Expectation::True)
+        );
+        let neg = BinarySymExpr::new(&sum, &Conc::new(0, int), Opcode::LE);
+        assert_eq!(
+            expect(cond.as_ref(), neg.as_ref(), &target),
+            Verdict::Expected(Expectation::False)
+        );
+    }
+
+    #[test]
+    fn test_expectFloat() {
+        let target = TargetInfo::default();
+        let f = Sym::new("f", "float");
+        let d = Sym::new("d", "double");
+        let gt = BinarySymExpr::new(&f, &Conc::new(10, CDataTypes::Float), Opcode::GT);
+
+        // Seen both ways on actual values of f.
+        let lt = BinarySymExpr::new(&f, &Conc::new(20, CDataTypes::Float), Opcode::LT);
+        assert_eq!(
+            expect(gt.as_ref(), lt.as_ref(), &target),
+            Verdict::Expected(Expectation::Unknown)
+        );
+
+        // Values between the integers are not tried.
+        let ge = BinarySymExpr::new(&f, &Conc::new(11, CDataTypes::Float), Opcode::GE);
+        assert_eq!(
+            expect(gt.as_ref(), ge.as_ref(), &target),
+            Verdict::Undecided
+        );
+
+        // Converting huge values of d to int is not modeled.
+        let huge = Conc::wrapping(10i128.pow(20), CDataTypes::Double, &target);
+        let cond = BinarySymExpr::new(&d, &huge, Opcode::GT);
+        let cast = UnarySymExpr::new(&d, UnaryOpcode::Cast(CDataTypes::Int));
+        let pos = BinarySymExpr::new(&cast, &Conc::new(0, CDataTypes::Int), Opcode::GT);
+        assert_eq!(
+            expect(cond.as_ref(), pos.as_ref(), &target),
+            Verdict::Undecided
+        );
+        assert_eq!(feasibility(cond.as_ref(), &target), Feasibility::Feasible);
+    }
+}
@@ -0,0 +1,432 @@
+//! Parse : Read C expression strings into expression trees.
+//!
+//! The grammar is the subset of C expressions symi can represent: integer
+//! literals, symbols, parentheses, the unary operators `-`, `~` and `!`,
+//! casts to arithmetic types, and every binary operator of BinOp with C
+//! precedence and associativity. Symbols are typed through a symbol table,
+//! and literals get the type C gives them from their value and suffix.
+//!
+//! # Examples
+//! ```
+//! use symi::DataType::CDataTypes;
+//! use symi::Parse::{parse, SymbolTable};
+//! use symi::Target::TargetInfo;
+//!
+//! let symbols = SymbolTable::from([
+//!     ("a".to_string(), CDataTypes::UnsignedInt),
+//!     ("b".to_string(), CDataTypes::UnsignedInt),
+//! ]);
+//! let target = TargetInfo::default();
+//! let expr = parse("(a % b) < 50 && b <= 30", &symbols, &target).unwrap();
+//! assert_eq!(expr.to_string(), "(((a % b) < 50) && (b <= 30))");
+//! ```
+
+use crate::BinOp::Opcode;
+use crate::DataType::CDataTypes;
+use crate::Expr::Expr;
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::UnOp::UnaryOpcode;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Types of the symbols an expression may refer to, by name.
+pub type SymbolTable = HashMap<String, CDataTypes>;
+
+/// Reasons a string is not an expression symi can represent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// A character which starts no token, and its byte offset.
+    UnexpectedChar(char, usize),
+    /// A token which does not fit the grammar at its position.
+    UnexpectedToken(String),
+    /// The input ends in the middle of an expression.
+    UnexpectedEnd,
+    /// A symbol missing from the symbol table.
+    Undeclared(String),
+    /// An integer literal which is malformed or fits no type.
+    InvalidLiteral(String),
+    /// A cast to something which is not an arithmetic type.
+    UnknownType(String),
+    /// An operator which needs integers, applied to a floating operand.
+    FloatingOperand(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedChar(c, at) => {
+                write!(f, "unexpected character '{}' at offset {}", c, at)
+            }
+            ParseError::UnexpectedToken(token) => write!(f, "unexpected '{}'", token),
+            ParseError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ParseError::Undeclared(name) => write!(f, "use of undeclared symbol {}", name),
+            ParseError::InvalidLiteral(literal) => write!(f, "invalid integer literal {}", literal),
+            ParseError::UnknownType(ty) => write!(f, "unknown type name {}", ty),
+            ParseError::FloatingOperand(op) => {
+                write!(f, "invalid floating operand of {}", op)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Punct(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) | Token::Number(s) => write!(f, "{}", s),
+            Token::Punct(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+// Longest first, so that `<<` is not read as two `<`.
+const PUNCTUATORS: [&str; 25] = [
+    "<<=", ">>=", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*", "/", "%", "+", "-", "<",
+    ">", "&", "^", "|", "=", "~", "!", "(", ")",
+];
+
+fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = vec![];
+    let mut rest = src;
+    while let Some(c) = rest.chars().next() {
+        let at = src.len() - rest.len();
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = rest[..len].to_string();
+            tokens.push(if c.is_ascii_digit() {
+                Token::Number(word)
+            } else {
+                Token::Ident(word)
+            });
+            rest = &rest[len..];
+        } else if let Some(punct) = PUNCTUATORS.iter().find(|p| rest.starts_with(*p)) {
+            tokens.push(Token::Punct(punct));
+            rest = &rest[punct.len()..];
+        } else {
+            return Err(ParseError::UnexpectedChar(c, at));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns the type of an integer literal, as the first type of C11 6.4.4.1
+/// its value fits in on `target`.
+fn literalType(
+    val: i128,
+    decimal: bool,
+    unsigned: bool,
+    longs: usize,
+    target: &TargetInfo,
+) -> Option<CDataTypes> {
+    use CDataTypes::*;
+    let candidates: &[CDataTypes] = match (unsigned, longs) {
+        (false, 0) if decimal => &[Int, Long, LongLong],
+        (false, 0) => &[
+            Int,
+            UnsignedInt,
+            Long,
+            UnsignedLong,
+            LongLong,
+            UnsignedLongLong,
+        ],
+        (false, 1) if decimal => &[Long, LongLong],
+        (false, 1) => &[Long, UnsignedLong, LongLong, UnsignedLongLong],
+        (false, _) if decimal => &[LongLong],
+        (false, _) => &[LongLong, UnsignedLongLong],
+        (true, 0) => &[UnsignedInt, UnsignedLong, UnsignedLongLong],
+        (true, 1) => &[UnsignedLong, UnsignedLongLong],
+        (true, _) => &[UnsignedLongLong],
+    };
+    candidates
+        .iter()
+        .copied()
+        .find(|ty| val <= ty.getMax(target))
+}
+
+fn parseLiteral(literal: &str, target: &TargetInfo) -> Result<Arc<Conc>, ParseError> {
+    let invalid = || ParseError::InvalidLiteral(literal.to_string());
+
+    let digits = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    let suffix = literal[digits.len()..].to_lowercase();
+    let unsigned = suffix.contains('u');
+    let longs = suffix.matches('l').count();
+    if !matches!(
+        suffix.as_str(),
+        "" | "u" | "l" | "ul" | "lu" | "ll" | "ull" | "llu"
+    ) || (longs == 2 && !literal.contains("ll") && !literal.contains("LL"))
+    {
+        return Err(invalid());
+    }
+
+    let (radix, body) = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        (16, hex)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (8, &digits[1..])
+    } else {
+        (10, digits)
+    };
+    let val = i128::from_str_radix(body, radix).map_err(|_| invalid())?;
+    let ty = literalType(val, radix == 10, unsigned, longs, target).ok_or_else(invalid)?;
+    Conc::tryNew(val, ty, target).ok_or_else(invalid)
+}
+
+/// Spell a sequence of type specifiers the way CDataTypes::getType expects,
+/// e.g. `unsigned` as `unsigned int` and `long int` as `long`.
+fn typeName(words: &[String]) -> Option<CDataTypes> {
+    let mut words: Vec<&str> = words.iter().map(String::as_str).collect();
+    if words.len() > 1 && words.last() == Some(&"int") {
+        words.pop();
+    }
+    match words.as_slice() {
+        ["unsigned"] => Some(CDataTypes::UnsignedInt),
+        ["signed"] => Some(CDataTypes::Int),
+        ["signed", "short"] => Some(CDataTypes::Short),
+        ["signed", "long"] => Some(CDataTypes::Long),
+        ["signed", "long", "long"] => Some(CDataTypes::LongLong),
+        words => CDataTypes::getType(&words.join(" ")),
+    }
+}
+
+fn isTypeSpecifier(word: &str) -> bool {
+    matches!(
+        word,
+        "char" | "short" | "int" | "long" | "signed" | "unsigned" | "float" | "double"
+    )
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    symbols: &'a SymbolTable,
+    target: &'a TargetInfo,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token.ok_or(ParseError::UnexpectedEnd)
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), ParseError> {
+        match self.next()? {
+            Token::Punct(p) if p == punct => Ok(()),
+            token => Err(ParseError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn isInteger(&self, expr: &Expr) -> bool {
+        expr.getType(self.target).isInteger()
+    }
+
+    /// Binary operator at the current position, if any.
+    fn peekBinary(&self) -> Option<Opcode> {
+        match self.peek()? {
+            Token::Punct(p) => Opcode::getOpcode(p),
+            _ => None,
+        }
+    }
+
+    /// Parse binary operators binding at least as tightly as `min`, by
+    /// precedence climbing.
+    fn binary(&mut self, min: u32) -> Result<Arc<Expr>, ParseError> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peekBinary() {
+            let prec = op.getPrecedence();
+            if prec < min {
+                break;
+            }
+            self.pos += 1;
+            let next = if op.isRightAssoc() { prec } else { prec + 1 };
+            let rhs = self.binary(next)?;
+            if op.needsIntegers() && !(self.isInteger(&lhs) && self.isInteger(&rhs)) {
+                return Err(ParseError::FloatingOperand(op.to_string()));
+            }
+            lhs = Expr::binary(&lhs, &rhs, op);
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Arc<Expr>, ParseError> {
+        let op = match self.peek() {
+            Some(Token::Punct("-")) => Some(UnaryOpcode::Neg),
+            Some(Token::Punct("~")) => Some(UnaryOpcode::Not),
+            Some(Token::Punct("!")) => Some(UnaryOpcode::LNot),
+            Some(Token::Punct("(")) => match self.tokens.get(self.pos + 1) {
+                Some(Token::Ident(word)) if isTypeSpecifier(word) => {
+                    self.pos += 1;
+                    Some(UnaryOpcode::Cast(self.castType()?))
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(op) = op else {
+            return self.primary();
+        };
+        if !matches!(op, UnaryOpcode::Cast(_)) {
+            self.pos += 1;
+        }
+
+        let operand = self.unary()?;
+        if op == UnaryOpcode::Not && !self.isInteger(&operand) {
+            return Err(ParseError::FloatingOperand(op.to_string()));
+        }
+        // A negated literal is folded into a literal of the same type. The
+        // type is at least int, so the negation neither promotes nor
+        // overflows, negated unsigned literals wrap around.
+        if let (UnaryOpcode::Neg, Expr::Conc(conc)) = (op, operand.as_ref()) {
+            let negated = Conc::wrapping(-conc.val, conc.ty, self.target);
+            return Ok(Arc::new(Expr::Conc(negated)));
+        }
+        Ok(Expr::unary(&operand, op))
+    }
+
+    /// Parse the type name of a cast and its closing parenthesis.
+    fn castType(&mut self) -> Result<CDataTypes, ParseError> {
+        let mut words = vec![];
+        while let Some(Token::Ident(word)) = self.peek() {
+            words.push(word.clone());
+            self.pos += 1;
+        }
+        self.expect(")")?;
+        typeName(&words).ok_or_else(|| ParseError::UnknownType(words.join(" ")))
+    }
+
+    fn primary(&mut self) -> Result<Arc<Expr>, ParseError> {
+        match self.next()? {
+            Token::Number(literal) => {
+                Ok(Arc::new(Expr::Conc(parseLiteral(&literal, self.target)?)))
+            }
+            Token::Ident(name) => match self.symbols.get(&name) {
+                Some(ty) => Ok(Arc::new(Expr::Sym(Arc::new(Sym {
+                    name,
+                    ty: *ty,
+                    range: None,
+                })))),
+                None => Err(ParseError::Undeclared(name)),
+            },
+            Token::Punct("(") => {
+                let expr = self.binary(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            token => Err(ParseError::UnexpectedToken(token.to_string())),
+        }
+    }
+}
+
+/// Parse a C expression whose symbols are typed by `symbols`. Literals are
+/// typed as on `target`.
+pub fn parse(
+    src: &str,
+    symbols: &SymbolTable,
+    target: &TargetInfo,
+) -> Result<Arc<Expr>, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        symbols,
+        target,
+    };
+    let expr = parser.binary(0)?;
+    match parser.peek() {
+        Some(token) => Err(ParseError::UnexpectedToken(token.to_string())),
+        None => Ok(expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::Fixture;
+
+    #[test]
+    fn test_parse() {
+        let fixture = Fixture::new(&[
+            ("x", CDataTypes::Int),
+            ("u", CDataTypes::UnsignedChar),
+            ("f", CDataTypes::Float),
+        ]);
+        let parsed =
+            |src: &str| parse(src, &fixture.symbols, &fixture.target).map(|expr| expr.to_string());
+
+        assert_eq!(
+            parsed("x + 2 * 3 - u << 1 < 4 == 1"),
+            Ok("(((((x + (2 * 3)) - u) << 1) < 4) == 1)".to_string())
+        );
+        assert_eq!(
+            parsed("x || u && x | u ^ x & 1"),
+            Ok("(x || (u && (x | (u ^ (x & 1)))))".to_string())
+        );
+        assert_eq!(parsed("x = u = 3"), Ok("(x = (u = 3))".to_string()));
+        assert_eq!(
+            parsed("-(unsigned char)~x != !(long)-2"),
+            Ok("((-((unsigned char)(~x))) != (!((long)-2)))".to_string())
+        );
+
+        // Literals take the first type their value fits in.
+        let literal = |src: &str| match fixture.parse(src).as_ref() {
+            Expr::Conc(conc) => (conc.val, conc.ty),
+            _ => panic!("{src} is not a literal"),
+        };
+        assert_eq!(literal("2147483647"), (2147483647, CDataTypes::Int));
+        assert_eq!(literal("0xffffffff"), (0xffffffff, CDataTypes::UnsignedInt));
+        assert_eq!(literal("-1u"), (0xffffffff, CDataTypes::UnsignedInt));
+        assert_eq!(literal("010LL"), (8, CDataTypes::LongLong));
+        assert_eq!(literal("-2147483648").1, CDataTypes::Long);
+
+        assert_eq!(parsed("y"), Err(ParseError::Undeclared("y".to_string())));
+        assert_eq!(parsed("x +"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parsed("(x"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(
+            parsed("x += 1"),
+            Err(ParseError::UnexpectedToken("=".to_string()))
+        );
+        assert_eq!(
+            parsed("x <<= 1"),
+            Err(ParseError::UnexpectedToken("<<=".to_string()))
+        );
+        assert_eq!(parsed("x ? 1 : 0"), Err(ParseError::UnexpectedChar('?', 2)));
+        assert_eq!(
+            parsed("1lL"),
+            Err(ParseError::InvalidLiteral("1lL".to_string()))
+        );
+        assert_eq!(
+            parsed("(bool)x"),
+            Err(ParseError::Undeclared("bool".to_string()))
+        );
+        assert_eq!(
+            parsed("(unsigned float)x"),
+            Err(ParseError::UnknownType("unsigned float".to_string()))
+        );
+        assert_eq!(parsed("f + 1"), Ok("(f + 1)".to_string()));
+        assert_eq!(
+            parsed("x << (double)u"),
+            Err(ParseError::FloatingOperand("<<".to_string()))
+        );
+        assert_eq!(
+            parsed("~f"),
+            Err(ParseError::FloatingOperand("~".to_string()))
+        );
+    }
+}
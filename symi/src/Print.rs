@@ -0,0 +1,228 @@
+//! Print : Write expressions as C with only the parentheses they need.
+//!
+//! fmt::Display parenthesizes every operation, which keeps the grouping of the
+//! tree obvious but reads nothing like a hand-written test. The printer here
+//! decides for each operand from the precedence and associativity of the
+//! operators around it, so that the printed expression parses back to the same
+//! tree.
+//!
+//! # Examples
+//! ```
+//! use symi::BinOp::Opcode;
+//! use symi::DataType::CDataTypes;
+//! use symi::Expr::BinarySymExpr;
+//! use symi::Print::{Parens, Print};
+//! use symi::Symbol::{Conc, Sym};
+//!
+//! let (a, b) = (Sym::new("a", "int"), Sym::new("b", "int"));
+//! let le = BinarySymExpr::new(&a, &Conc::new(30, CDataTypes::Int), Opcode::LE);
+//! let rem = BinarySymExpr::new(&a, &b, Opcode::Rem);
+//! let mul = BinarySymExpr::new(&le, &rem, Opcode::Mul);
+//! assert_eq!(mul.print(Parens::All), "(a <= 30) * (a % b)");
+//! assert_eq!(mul.print(Parens::Minimal), "(a <= 30) * (a % b)");
+//!
+//! let or = BinarySymExpr::new(&le, &BinarySymExpr::new(&a, &b, Opcode::LAnd), Opcode::LOr);
+//! assert_eq!(or.print(Parens::Minimal), "a <= 30 || a && b");
+//! assert_eq!(or.print(Parens::Warnings), "a <= 30 || (a && b)");
+//! ```
+
+use crate::BinOp::Opcode;
+use crate::Expr::{BinarySymExpr, Expr, UnarySymExpr};
+use crate::Symbol::{Conc, Sym};
+use crate::UnOp::UnaryOpcode;
+
+/// How much of the grouping of an expression is spelled out with parentheses.
+/// The outermost expression is never parenthesized, that is up to where it is
+/// printed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Parens {
+    /// Around every operation, as fmt::Display does.
+    All,
+    /// Only where C precedence and associativity require them.
+    Minimal,
+    /// Also where compilers warn about the grouping even though it is what C
+    /// does, e.g. `a || b && c` under -Wparentheses. Tests checked with
+    /// -verify must not raise unexpected warnings.
+    Warnings,
+}
+
+/// The operation at the root of an expression.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Root {
+    /// A symbol or a literal, never parenthesized.
+    Primary,
+    Unary(UnaryOpcode),
+    Binary(Opcode),
+}
+
+/// Expressions which can be printed with minimal parentheses.
+pub trait Print {
+    fn getRoot(&self) -> Root;
+
+    /// Returns the expression as C, parenthesized according to `parens`.
+    fn print(&self, parens: Parens) -> String;
+}
+
+/// Groupings clang warns about unless parenthesized: `&&` within `||`,
+/// `&` and `^` within `|`, `&` within `^`, `+` and `-` within shifts,
+/// comparisons within bitwise operators, chained comparisons, and `!x` on the
+/// left of a comparison.
+fn warns(parent: Opcode, child: Root, isRHS: bool) -> bool {
+    use Opcode::*;
+    match child {
+        Root::Primary => false,
+        Root::Unary(op) => op == UnaryOpcode::LNot && !isRHS && parent.isComparison(),
+        Root::Binary(op) => match parent {
+            LOr => op == LAnd,
+            Or => matches!(op, And | Xor) || op.isComparison(),
+            Xor => op == And || op.isComparison(),
+            And => op.isComparison(),
+            Shl | Shr => matches!(op, Add | Sub),
+            _ => parent.isComparison() && op.isComparison(),
+        },
+    }
+}
+
+/// Whether `child` needs parentheses as an operand of `parent`.
+pub fn needsParens(parent: Root, child: Root, isRHS: bool, parens: Parens) -> bool {
+    match (parent, child) {
+        (_, Root::Primary) => false,
+        _ if parens == Parens::All => true,
+        (Root::Unary(_), Root::Binary(_)) => true,
+        // `-(-x)` printed as `--x` would read as a decrement.
+        (Root::Unary(UnaryOpcode::Neg), Root::Unary(UnaryOpcode::Neg)) => true,
+        (Root::Unary(_), _) => false,
+        (Root::Binary(op), Root::Binary(child)) => {
+            let (prec, childPrec) = (op.getPrecedence(), child.getPrecedence());
+            childPrec < prec
+                || (childPrec == prec && isRHS != op.isRightAssoc())
+                || (parens == Parens::Warnings && warns(op, Root::Binary(child), isRHS))
+        }
+        (Root::Binary(op), _) => parens == Parens::Warnings && warns(op, child, isRHS),
+        (Root::Primary, _) => false,
+    }
+}
+
+fn operand<T: Print>(parent: Root, child: &T, isRHS: bool, parens: Parens) -> String {
+    if needsParens(parent, child.getRoot(), isRHS, parens) {
+        format!("({})", child.print(parens))
+    } else {
+        child.print(parens)
+    }
+}
+
+impl Print for Sym {
+    fn getRoot(&self) -> Root {
+        Root::Primary
+    }
+
+    fn print(&self, _parens: Parens) -> String {
+        self.name.clone()
+    }
+}
+
+impl Print for Conc {
+    /// A negative literal is a negation as far as grouping goes.
+    fn getRoot(&self) -> Root {
+        if self.literal().starts_with('-') {
+            Root::Unary(UnaryOpcode::Neg)
+        } else {
+            Root::Primary
+        }
+    }
+
+    fn print(&self, _parens: Parens) -> String {
+        self.literal()
+    }
+}
+
+impl<T: Print> Print for UnarySymExpr<T> {
+    fn getRoot(&self) -> Root {
+        Root::Unary(self.getOpcode())
+    }
+
+    fn print(&self, parens: Parens) -> String {
+        let operand = operand(self.getRoot(), self.getOperand().as_ref(), false, parens);
+        format!("{}{}", self.getOpcode(), operand)
+    }
+}
+
+impl<T1: Print, T2: Print> Print for BinarySymExpr<T1, T2> {
+    fn getRoot(&self) -> Root {
+        Root::Binary(self.getOpcode())
+    }
+
+    fn print(&self, parens: Parens) -> String {
+        let root = self.getRoot();
+        format!(
+            "{} {} {}",
+            operand(root, self.getLHS().as_ref(), false, parens),
+            self.getOpcode(),
+            operand(root, self.getRHS().as_ref(), true, parens)
+        )
+    }
+}
+
+impl Print for Expr {
+    fn getRoot(&self) -> Root {
+        match self {
+            Expr::Sym(sym) => sym.getRoot(),
+            Expr::Conc(conc) => conc.getRoot(),
+            Expr::Binary(bse) => bse.getRoot(),
+            Expr::Unary(unary) => unary.getRoot(),
+        }
+    }
+
+    fn print(&self, parens: Parens) -> String {
+        match self {
+            Expr::Sym(sym) => sym.print(parens),
+            Expr::Conc(conc) => conc.print(parens),
+            Expr::Binary(bse) => bse.print(parens),
+            Expr::Unary(unary) => unary.print(parens),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::Fixture;
+    use crate::DataType::CDataTypes;
+
+    #[test]
+    fn test_print() {
+        let fixture = Fixture::new(&[("a", CDataTypes::Int), ("b", CDataTypes::UnsignedChar)]);
+        let print = |src: &str, parens: Parens| fixture.parse(src).print(parens);
+
+        let src = "a - (b - 1) - -a * -(-a) << 2 + b";
+        assert_eq!(print(src, Parens::Minimal), src);
+        assert_eq!(
+            print(src, Parens::Warnings),
+            "(a - (b - 1) - -a * -(-a)) << (2 + b)"
+        );
+        assert_eq!(
+            print(src, Parens::All),
+            "((a - (b - 1)) - ((-a) * (-(-a)))) << (2 + b)"
+        );
+
+        let src = "(a = b = 1) == 1 < (a & b | a ^ b) && !a != 0 || -1";
+        assert_eq!(print(src, Parens::Minimal), src);
+        assert_eq!(
+            print(src, Parens::Warnings),
+            "((a = b = 1) == (1 < ((a & b) | (a ^ b))) && (!a) != 0) || -1"
+        );
+
+        // Printing and parsing again gives the same tree.
+        for src in [
+            "(unsigned char)(a + b) * (long)-a",
+            "~(a << b) >> (b >> a) == (a & (b & a))",
+            "-2147483647 - 1 - (-2147483647 - 1)",
+        ] {
+            for parens in [Parens::All, Parens::Minimal, Parens::Warnings] {
+                let expr = fixture.parse(src);
+                let again = fixture.parse(&expr.print(parens));
+                assert_eq!(again.to_string(), expr.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,623 @@
+//! Range : Sets of values a symbol can take, modeled on the RangeSet of the
+//! Clang Static Analyzer's RangeConstraintManager.
+//!
+//! A RangeSet is a sorted list of disjoint closed intervals. Conditionals are
+//! turned into one RangeSet per symbol, e.g.
+//!
+//! ```text
+//!     x > 0 && x < 10 && y > 11 && y < 20   ->   x: [1, 9], y: [12, 19]
+//! ```
+
+use crate::BinOp::Opcode;
+use crate::DataType::CDataTypes;
+use crate::Eval::{evalUnary, Eval, Leaf};
+use crate::Expr::{BinarySymExpr, Expr, UnarySymExpr};
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::UnOp::UnaryOpcode;
+use std::fmt;
+
+/// A set of integers stored as sorted, disjoint and non-adjacent closed
+/// intervals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeSet {
+    ranges: Vec<(i128, i128)>,
+}
+
+impl RangeSet {
+    pub fn empty() -> RangeSet {
+        RangeSet { ranges: vec![] }
+    }
+
+    /// A set containing every value in `[lo, hi]`. Empty if `lo > hi`.
+    pub fn new(lo: i128, hi: i128) -> RangeSet {
+        RangeSet::fromRanges(vec![(lo, hi)])
+    }
+
+    pub fn point(val: i128) -> RangeSet {
+        RangeSet::new(val, val)
+    }
+
+    /// All values of a type on `target`. Those of floating types go beyond
+    /// i128, which then stands for all of them.
+    pub fn full(ty: CDataTypes, target: &TargetInfo) -> RangeSet {
+        if !ty.isInteger() {
+            return RangeSet::new(i128::MIN, i128::MAX);
+        }
+        RangeSet::new(ty.getMin(target), ty.getMax(target))
+    }
+
+    /// Builds a set out of arbitrary intervals. Empty intervals are dropped,
+    /// overlapping and adjacent ones are merged.
+    pub fn fromRanges(mut ranges: Vec<(i128, i128)>) -> RangeSet {
+        ranges.retain(|(lo, hi)| lo <= hi);
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(i128, i128)> = Vec::with_capacity(ranges.len());
+        for (lo, hi) in ranges {
+            match merged.last_mut() {
+                Some(last) if lo <= last.1.saturating_add(1) => last.1 = last.1.max(hi),
+                _ => merged.push((lo, hi)),
+            }
+        }
+
+        RangeSet { ranges: merged }
+    }
+
+    pub fn getRanges(&self) -> &[(i128, i128)] {
+        &self.ranges
+    }
+
+    pub fn isEmpty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn getMin(&self) -> Option<i128> {
+        self.ranges.first().map(|r| r.0)
+    }
+
+    pub fn getMax(&self) -> Option<i128> {
+        self.ranges.last().map(|r| r.1)
+    }
+
+    /// Returns the only value of the set, if it has exactly one.
+    pub fn getConcreteValue(&self) -> Option<i128> {
+        match self.ranges[..] {
+            [(lo, hi)] if lo == hi => Some(lo),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, val: i128) -> bool {
+        self.ranges.iter().any(|(lo, hi)| *lo <= val && val <= *hi)
+    }
+
+    /// Number of values in the set.
+    pub fn size(&self) -> u128 {
+        self.ranges
+            .iter()
+            .map(|(lo, hi)| hi.abs_diff(*lo) + 1)
+            .sum()
+    }
+
+    pub fn union(&self, other: &RangeSet) -> RangeSet {
+        let mut ranges = self.ranges.clone();
+        ranges.extend_from_slice(&other.ranges);
+        RangeSet::fromRanges(ranges)
+    }
+
+    pub fn intersect(&self, other: &RangeSet) -> RangeSet {
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (lo1, hi1) = self.ranges[i];
+            let (lo2, hi2) = other.ranges[j];
+            if lo1.max(lo2) <= hi1.min(hi2) {
+                ranges.push((lo1.max(lo2), hi1.min(hi2)));
+            }
+
+            if hi1 < hi2 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        RangeSet { ranges }
+    }
+
+    /// All values of type `ty` which are not in the set.
+    pub fn complement(&self, ty: CDataTypes, target: &TargetInfo) -> RangeSet {
+        let mut ranges = vec![];
+        let mut from = ty.getMin(target);
+        for (lo, hi) in self.intersect(&RangeSet::full(ty, target)).ranges {
+            ranges.push((from, lo - 1));
+            from = hi + 1;
+        }
+        ranges.push((from, ty.getMax(target)));
+
+        RangeSet::fromRanges(ranges)
+    }
+
+    /// Adds `offset` to every value of the set.
+    pub fn shift(&self, offset: i128) -> RangeSet {
+        RangeSet {
+            ranges: self
+                .ranges
+                .iter()
+                .map(|(lo, hi)| (lo + offset, hi + offset))
+                .collect(),
+        }
+    }
+
+    /// Values `v` of type `ty` for which `(common) v` is in this set, where
+    /// `common` is a type `ty` converts to without loss of rank.
+    pub fn preimage(&self, ty: CDataTypes, common: CDataTypes, target: &TargetInfo) -> RangeSet {
+        if !ty.isSigned(target) || common.isSigned(target) {
+            return self.intersect(&RangeSet::full(ty, target));
+        }
+
+        // Negative values of a signed type wrap around to the upper half of
+        // the unsigned common type.
+        let modulus = 1i128 << common.getWidth(target);
+        let nonNegative = self.intersect(&RangeSet::new(0, ty.getMax(target)));
+        let negative = self
+            .intersect(&RangeSet::new(modulus + ty.getMin(target), modulus - 1))
+            .shift(-modulus);
+        nonNegative.union(&negative)
+    }
+}
+
+impl fmt::Display for RangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ranges.is_empty() {
+            return write!(f, "{{}}");
+        }
+
+        let ranges: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|(lo, hi)| format!("[{}, {}]", lo, hi))
+            .collect();
+        write!(f, "{}", ranges.join(" U "))
+    }
+}
+
+/// Ranges of the symbols constrained by a conditional. Symbols absent from it
+/// may take any value of their type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constraints {
+    syms: Vec<(String, CDataTypes, RangeSet)>,
+    feasible: bool,
+}
+
+impl Constraints {
+    /// No symbol is constrained.
+    pub fn new() -> Constraints {
+        Constraints {
+            syms: vec![],
+            feasible: true,
+        }
+    }
+
+    /// The conditional can never be true.
+    pub fn infeasible() -> Constraints {
+        Constraints {
+            syms: vec![],
+            feasible: false,
+        }
+    }
+
+    pub fn isFeasible(&self) -> bool {
+        self.feasible
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RangeSet> {
+        self.syms
+            .iter()
+            .find(|(sym, _, _)| sym == name)
+            .map(|(_, _, range)| range)
+    }
+
+    /// Range of `sym` under these constraints.
+    pub fn getRangeOf(&self, sym: &Sym, target: &TargetInfo) -> RangeSet {
+        match self.get(&sym.name) {
+            Some(range) => range.clone(),
+            None => sym.getRangeSet(target),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, CDataTypes, &RangeSet)> {
+        self.syms.iter().map(|(name, ty, range)| (name, *ty, range))
+    }
+
+    /// Restrict symbol `name` to values in `range`.
+    pub fn constrain(&mut self, name: &str, ty: CDataTypes, range: &RangeSet) {
+        if !self.feasible {
+            return;
+        }
+
+        match self.syms.iter_mut().find(|(sym, _, _)| sym == name) {
+            Some((_, _, old)) => *old = old.intersect(range),
+            None => self.syms.push((name.to_string(), ty, range.clone())),
+        }
+
+        if range.isEmpty() || self.get(name).is_some_and(|r| r.isEmpty()) {
+            *self = Constraints::infeasible();
+        }
+    }
+
+    /// Constraints under which both `self` and `other` hold.
+    pub fn and(&self, other: &Constraints) -> Constraints {
+        if !self.feasible || !other.feasible {
+            return Constraints::infeasible();
+        }
+
+        let mut ret = self.clone();
+        for (name, ty, range) in other.syms.iter() {
+            ret.constrain(name, *ty, range);
+        }
+        ret
+    }
+
+    /// Constraints under which either `self` or `other` holds. This is an
+    /// over-approximation: a symbol constrained on one side only becomes
+    /// unconstrained.
+    pub fn or(&self, other: &Constraints) -> Constraints {
+        if !self.feasible {
+            return other.clone();
+        }
+        if !other.feasible {
+            return self.clone();
+        }
+
+        let mut ret = Constraints::new();
+        for (name, ty, range) in self.syms.iter() {
+            if let Some(otherRange) = other.get(name) {
+                ret.syms.push((name.clone(), *ty, range.union(otherRange)));
+            }
+        }
+        ret
+    }
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Constraints::new()
+    }
+}
+
+impl fmt::Display for Constraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let syms: Vec<String> = self
+            .syms
+            .iter()
+            .map(|(name, _, range)| format!("{}: {}", name, range))
+            .collect();
+        write!(f, "{}", syms.join(", "))
+    }
+}
+
+/// Expressions from which symbol ranges can be derived when used as a
+/// conditional.
+pub trait Constrain: Eval {
+    /// Returns the ranges of symbols under which this expression is true on
+    /// `target`.
+    fn constraints(&self, target: &TargetInfo) -> Constraints;
+
+    /// Returns the ranges of symbols under which this expression is false on
+    /// `target`. Unless an expression knows better, they are unconstrained.
+    fn negatedConstraints(&self, _target: &TargetInfo) -> Constraints {
+        Constraints::new()
+    }
+
+    /// Returns Some if the expression is a single symbol or constant.
+    fn asLeaf(&self) -> Option<Leaf<'_>> {
+        None
+    }
+}
+
+impl Constrain for Sym {
+    fn constraints(&self, target: &TargetInfo) -> Constraints {
+        let mut ret = Constraints::new();
+        if self.ty.isInteger() {
+            let range = RangeSet::point(0).complement(self.ty, target);
+            ret.constrain(&self.name, self.ty, &range);
+        }
+        ret
+    }
+
+    fn negatedConstraints(&self, _target: &TargetInfo) -> Constraints {
+        let mut ret = Constraints::new();
+        if self.ty.isInteger() {
+            ret.constrain(&self.name, self.ty, &RangeSet::point(0));
+        }
+        ret
+    }
+
+    fn asLeaf(&self) -> Option<Leaf<'_>> {
+        Some(Leaf::Sym(self))
+    }
+}
+
+impl Constrain for Conc {
+    fn constraints(&self, _target: &TargetInfo) -> Constraints {
+        if self.val == 0 {
+            Constraints::infeasible()
+        } else {
+            Constraints::new()
+        }
+    }
+
+    fn negatedConstraints(&self, _target: &TargetInfo) -> Constraints {
+        if self.val == 0 {
+            Constraints::new()
+        } else {
+            Constraints::infeasible()
+        }
+    }
+
+    fn asLeaf(&self) -> Option<Leaf<'_>> {
+        Some(Leaf::Conc(self.getValue()))
+    }
+}
+
+impl Constrain for Expr {
+    fn constraints(&self, target: &TargetInfo) -> Constraints {
+        match self {
+            Expr::Sym(sym) => sym.constraints(target),
+            Expr::Conc(conc) => conc.constraints(target),
+            Expr::Binary(bse) => bse.constraints(target),
+            Expr::Unary(unary) => unary.constraints(target),
+        }
+    }
+
+    fn negatedConstraints(&self, target: &TargetInfo) -> Constraints {
+        match self {
+            Expr::Sym(sym) => sym.negatedConstraints(target),
+            Expr::Conc(conc) => conc.negatedConstraints(target),
+            Expr::Binary(bse) => bse.negatedConstraints(target),
+            Expr::Unary(unary) => unary.negatedConstraints(target),
+        }
+    }
+
+    fn asLeaf(&self) -> Option<Leaf<'_>> {
+        match self {
+            Expr::Sym(sym) => sym.asLeaf(),
+            Expr::Conc(conc) => conc.asLeaf(),
+            Expr::Binary(bse) => bse.asLeaf(),
+            Expr::Unary(unary) => unary.asLeaf(),
+        }
+    }
+}
+
+impl<T> Constrain for UnarySymExpr<T>
+where
+    T: Constrain,
+{
+    fn constraints(&self, target: &TargetInfo) -> Constraints {
+        // Only unary operators applied to a symbol or a constant are
+        // understood.
+        let sym = match self.getOperand().asLeaf() {
+            Some(Leaf::Sym(sym)) if sym.ty.isInteger() => sym,
+            Some(Leaf::Conc(conc)) => {
+                return match evalUnary(self.getOpcode(), conc, target) {
+                    Ok(val) if !val.isTrue() => Constraints::infeasible(),
+                    _ => Constraints::new(),
+                }
+            }
+            _ => return Constraints::new(),
+        };
+
+        let promoted = sym.ty.promote();
+        let range = match self.getOpcode() {
+            UnaryOpcode::Neg => RangeSet::point(0).complement(sym.ty, target),
+            UnaryOpcode::Not => RangeSet::point(promoted.wrap(-1, target))
+                .complement(promoted, target)
+                .preimage(sym.ty, promoted, target),
+            UnaryOpcode::LNot => RangeSet::point(0),
+            // Values of a narrowing cast are not contiguous in the symbol's
+            // type, those casts are left unconstrained.
+            UnaryOpcode::Cast(ty)
+                if ty.isInteger()
+                    && (ty.getWidth(target) > sym.ty.getWidth(target)
+                        || (ty.getWidth(target) == sym.ty.getWidth(target)
+                            && (sym.ty.isSigned(target) || !ty.isSigned(target)))) =>
+            {
+                RangeSet::point(0)
+                    .complement(ty, target)
+                    .preimage(sym.ty, ty, target)
+            }
+            UnaryOpcode::Cast(_) => return Constraints::new(),
+        };
+
+        let mut ret = Constraints::new();
+        ret.constrain(&sym.name, sym.ty, &range);
+        ret
+    }
+
+    fn negatedConstraints(&self, target: &TargetInfo) -> Constraints {
+        match self.getOpcode() {
+            UnaryOpcode::LNot => self.getOperand().constraints(target),
+            _ => Constraints::new(),
+        }
+    }
+}
+
+impl<T1, T2> Constrain for BinarySymExpr<T1, T2>
+where
+    T1: Constrain,
+    T2: Constrain,
+{
+    fn constraints(&self, target: &TargetInfo) -> Constraints {
+        let op = self.getOpcode();
+        match op {
+            Opcode::LAnd => {
+                return self
+                    .getLHS()
+                    .constraints(target)
+                    .and(&self.getRHS().constraints(target))
+            }
+            Opcode::LOr => {
+                return self
+                    .getLHS()
+                    .constraints(target)
+                    .or(&self.getRHS().constraints(target))
+            }
+            _ => {}
+        }
+        comparison(self, op, target)
+    }
+
+    fn negatedConstraints(&self, target: &TargetInfo) -> Constraints {
+        match self.getOpcode() {
+            Opcode::LAnd => self
+                .getLHS()
+                .negatedConstraints(target)
+                .or(&self.getRHS().negatedConstraints(target)),
+            Opcode::LOr => self
+                .getLHS()
+                .negatedConstraints(target)
+                .and(&self.getRHS().negatedConstraints(target)),
+            op => match op.getInverse() {
+                Some(inverse) => comparison(self, inverse, target),
+                None => Constraints::new(),
+            },
+        }
+    }
+}
+
+/// Returns the ranges of symbols under which `LHS Op RHS` is true, where LHS
+/// and RHS are the operands of `bse`.
+fn comparison<T1, T2>(bse: &BinarySymExpr<T1, T2>, op: Opcode, target: &TargetInfo) -> Constraints
+where
+    T1: Constrain,
+    T2: Constrain,
+{
+    // Only comparisons between a symbol and a constant are understood.
+    let (sym, conc, op) = match (bse.getLHS().asLeaf(), bse.getRHS().asLeaf()) {
+        (Some(Leaf::Sym(sym)), Some(Leaf::Conc(conc))) => (sym, conc, op),
+        (Some(Leaf::Conc(conc)), Some(Leaf::Sym(sym))) => match op.getSwapped() {
+            Some(swapped) => (sym, conc, swapped),
+            None => return Constraints::new(),
+        },
+        _ => return Constraints::new(),
+    };
+    if !sym.ty.isInteger() || !conc.ty.isInteger() {
+        return Constraints::new();
+    }
+
+    let common = sym.ty.getCommonType(conc.ty, target);
+    let (C_MIN, C_MAX) = common.getRange(target);
+    let val = conc.cast(common, target).val;
+    let range = match op {
+        Opcode::LT => RangeSet::new(C_MIN, val - 1),
+        Opcode::GT => RangeSet::new(val + 1, C_MAX),
+        Opcode::LE => RangeSet::new(C_MIN, val),
+        Opcode::GE => RangeSet::new(val, C_MAX),
+        Opcode::EQ => RangeSet::point(val),
+        Opcode::NE => RangeSet::point(val).complement(common, target),
+        _ => return Constraints::new(),
+    };
+
+    let mut ret = Constraints::new();
+    ret.constrain(&sym.name, sym.ty, &range.preimage(sym.ty, common, target));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_RangeSetOps() {
+        let target = TargetInfo::default();
+        let a = RangeSet::fromRanges(vec![(5, 9), (1, 3), (4, 4), (20, 30)]);
+        assert_eq!(a.getRanges(), &[(1, 9), (20, 30)]);
+        assert_eq!(a.size(), 20);
+
+        let b = RangeSet::new(8, 25);
+        assert_eq!(a.intersect(&b).getRanges(), &[(8, 9), (20, 25)]);
+        assert_eq!(a.union(&b).getRanges(), &[(1, 30)]);
+
+        let c = a.complement(CDataTypes::UnsignedChar, &target);
+        assert_eq!(c.getRanges(), &[(0, 0), (10, 19), (31, 255)]);
+        assert_eq!(c.complement(CDataTypes::UnsignedChar, &target), a);
+        assert!(
+            RangeSet::empty().complement(CDataTypes::Int, &target)
+                == RangeSet::full(CDataTypes::Int, &target)
+        );
+        assert_eq!(format!("{}", a), "[1, 9] U [20, 30]");
+    }
+
+    #[test]
+    fn test_constraints() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "int");
+        let y = Sym::new("y", "int");
+        let c0 = Conc::new(0, CDataTypes::Int);
+        let c10 = Conc::new(10, CDataTypes::Int);
+        let c11 = Conc::new(11, CDataTypes::Int);
+        let c20 = Conc::new(20, CDataTypes::Int);
+
+        let x0 = BinarySymExpr::new(&x, &c0, Opcode::GT);
+        let x10 = BinarySymExpr::new(&x, &c10, Opcode::LT);
+        let y11 = BinarySymExpr::new(&c11, &y, Opcode::LT);
+        let y20 = BinarySymExpr::new(&y, &c20, Opcode::LT);
+        let xs = BinarySymExpr::new(&x0, &x10, Opcode::LAnd);
+        let ys = BinarySymExpr::new(&y11, &y20, Opcode::LAnd);
+        let cond = BinarySymExpr::new(&xs, &ys, Opcode::LAnd);
+        assert_eq!(
+            cond.constraints(&target).to_string(),
+            "x: [1, 9], y: [12, 19]"
+        );
+
+        let either = BinarySymExpr::new(&xs, &x0, Opcode::LOr);
+        assert_eq!(
+            either.constraints(&target).to_string(),
+            "x: [1, 2147483647]"
+        );
+        let either = BinarySymExpr::new(&xs, &ys, Opcode::LOr);
+        assert_eq!(either.constraints(&target), Constraints::new());
+
+        let never = BinarySymExpr::new(&x10, &y11, Opcode::LAnd);
+        let never = BinarySymExpr::new(&never, &x0, Opcode::LAnd);
+        let never = BinarySymExpr::new(&never, &c0, Opcode::LAnd);
+        assert!(!never.constraints(&target).isFeasible());
+
+        // Where the conditions are false.
+        assert_eq!(
+            xs.negatedConstraints(&target).to_string(),
+            "x: [-2147483648, 0] U [10, 2147483647]"
+        );
+        let neither = BinarySymExpr::new(&x10, &y11, Opcode::LOr);
+        assert_eq!(
+            neither.negatedConstraints(&target).to_string(),
+            "x: [10, 2147483647], y: [-2147483648, 11]"
+        );
+        assert_eq!(x.negatedConstraints(&target).to_string(), "x: [0, 0]");
+        let sum = BinarySymExpr::new(&x, &y, Opcode::Add);
+        assert_eq!(sum.negatedConstraints(&target), Constraints::new());
+    }
+
+    #[test]
+    fn test_constraintsConversions() {
+        let target = TargetInfo::default();
+        // 252 + 3 does not fit in an unsigned char, the branch is dead.
+        let uch = Sym::new("uch", "unsigned char");
+        let c255 = Conc::new(255 + 3, CDataTypes::Int);
+        let ge = BinarySymExpr::new(&uch, &c255, Opcode::GE);
+        assert!(!ge.constraints(&target).isFeasible());
+
+        // Negative ints compare as huge unsigned values.
+        let s = Sym::new("s", "int");
+        let u5 = Conc::new(5, CDataTypes::UnsignedInt);
+        let lt = BinarySymExpr::new(&s, &u5, Opcode::LT);
+        assert_eq!(lt.constraints(&target).to_string(), "s: [0, 4]");
+        let gt = BinarySymExpr::new(&s, &u5, Opcode::GT);
+        assert_eq!(
+            gt.constraints(&target).to_string(),
+            "s: [-2147483648, -1] U [6, 2147483647]"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Smt : Export expressions and tests as SMT-LIB2 bit-vector queries.
+//!
+//! Every C value becomes a bit-vector as wide as its type, and every operator
+//! the `bv*` operation of the signedness of its operands after the integer
+//! promotions and the usual arithmetic conversions. Comparisons and logical
+//! operators produce an `int` of 0 or 1, as in C. This is close to what the
+//! analyzer hands to Z3 when built with the Z3 constraint manager, and gives a
+//! ground truth independent of the Oracle which any solver can check.
+//!
+//! Operations with undefined behavior (division by zero, out-of-range shifts)
+//! are not paths the analyzer follows. Each of them adds a guard to the query
+//! which rules those values out, the same way the Oracle skips them.
+//!
+//! # Examples
+//! ```
+//! use symi::BinOp::Opcode;
+//! use symi::DataType::CDataTypes;
+//! use symi::Expr::BinarySymExpr;
+//! use symi::Smt::Smt;
+//! use symi::Symbol::{Conc, Sym};
+//! use symi::Target::TargetInfo;
+//!
+//! let x = Sym::new("x", "unsigned char");
+//! let lt = BinarySymExpr::new(&x, &Conc::new(10, CDataTypes::Int), Opcode::LT);
+//! let script = lt.toSmtScript(&TargetInfo::default()).unwrap();
+//! assert!(script.contains("(declare-const x (_ BitVec 8))"));
+//! assert!(script.contains("(bvslt ((_ zero_extend 24) x) (_ bv10 32))"));
+//! ```
+
+use crate::BinOp::Opcode;
+use crate::DataType::CDataTypes;
+use crate::Eval::{Eval, Leaf};
+use crate::Expr::{BinarySymExpr, Expr, UnarySymExpr};
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::UnOp::UnaryOpcode;
+use std::fmt::Write;
+
+/// A bit-vector term and the C type of its value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Term {
+    pub ty: CDataTypes,
+    pub text: String,
+}
+
+impl Term {
+    pub fn new(text: String, ty: CDataTypes) -> Term {
+        Term { ty, text }
+    }
+
+    /// Convert the value to another integer type, extending or truncating the
+    /// bit-vector as a C conversion does.
+    pub fn cast(&self, ty: CDataTypes, target: &TargetInfo) -> Term {
+        let (from, to) = (self.ty.getWidth(target), ty.getWidth(target));
+        let text = if to > from {
+            let extend = if self.ty.isSigned(target) {
+                "sign_extend"
+            } else {
+                "zero_extend"
+            };
+            format!("((_ {} {}) {})", extend, to - from, self.text)
+        } else if to < from {
+            format!("((_ extract {} 0) {})", to - 1, self.text)
+        } else {
+            self.text.clone()
+        };
+        Term::new(text, ty)
+    }
+
+    /// Boolean term which holds if the value is non-zero.
+    pub fn isTrue(&self, target: &TargetInfo) -> String {
+        format!("(distinct {} {})", self.text, literal(0, self.ty, target))
+    }
+
+    /// An int of 1 if `cond` holds, 0 otherwise.
+    fn fromBool(cond: String, target: &TargetInfo) -> Term {
+        let int = CDataTypes::Int;
+        Term::new(
+            format!(
+                "(ite {} {} {})",
+                cond,
+                literal(1, int, target),
+                literal(0, int, target)
+            ),
+            int,
+        )
+    }
+}
+
+/// Expressions which can be written as SMT-LIB2 terms.
+pub trait Smt: Eval {
+    /// Returns the term of the expression, or None if it involves a type
+    /// other than an integer. Conditions under which the expression is
+    /// defined are pushed to `guards`.
+    fn toSmtTerm(&self, guards: &mut Vec<String>, target: &TargetInfo) -> Option<Term>;
+
+    /// Returns a script checking whether the expression can be true on
+    /// `target`.
+    fn toSmtScript(&self, target: &TargetInfo) -> Option<String>
+    where
+        Self: Sized,
+    {
+        let mut guards = vec![];
+        let term = self.toSmtTerm(&mut guards, target)?;
+
+        let mut script = header(&[self as &dyn Eval], target);
+        for guard in guards {
+            writeln!(script, "(assert {})", guard).unwrap();
+        }
+        writeln!(script, "(assert {})", term.isTrue(target)).unwrap();
+        writeln!(script, "(check-sat)").unwrap();
+        Some(script)
+    }
+}
+
+/// Returns the logic and the declarations of all symbols of `exprs`.
+pub fn header(exprs: &[&dyn Eval], target: &TargetInfo) -> String {
+    let mut leaves = vec![];
+    for expr in exprs {
+        expr.leaves(&mut leaves, target);
+    }
+
+    let mut script = String::from("(set-logic QF_BV)\n");
+    let mut declared: Vec<&str> = vec![];
+    for leaf in leaves {
+        if let Leaf::Sym(sym) = leaf {
+            if !declared.contains(&sym.name.as_str()) {
+                declared.push(&sym.name);
+                writeln!(
+                    script,
+                    "(declare-const {} (_ BitVec {}))",
+                    sym.name,
+                    sym.ty.getWidth(target)
+                )
+                .unwrap();
+            }
+        }
+    }
+    script
+}
+
+/// Bit-vector literal of `val` in type `ty`.
+fn literal(val: i128, ty: CDataTypes, target: &TargetInfo) -> String {
+    let width = ty.getWidth(target);
+    let bits = (val as u128) & (u128::MAX >> (128 - width));
+    format!("(_ bv{} {})", bits, width)
+}
+
+impl Smt for Sym {
+    fn toSmtTerm(&self, _guards: &mut Vec<String>, _target: &TargetInfo) -> Option<Term> {
+        if !self.ty.isInteger() {
+            return None;
+        }
+        Some(Term::new(self.name.clone(), self.ty))
+    }
+}
+
+impl Smt for Conc {
+    fn toSmtTerm(&self, _guards: &mut Vec<String>, target: &TargetInfo) -> Option<Term> {
+        if !self.ty.isInteger() {
+            return None;
+        }
+        Some(Term::new(literal(self.val, self.ty, target), self.ty))
+    }
+}
+
+impl Smt for Expr {
+    fn toSmtTerm(&self, guards: &mut Vec<String>, target: &TargetInfo) -> Option<Term> {
+        match self {
+            Expr::Sym(sym) => sym.toSmtTerm(guards, target),
+            Expr::Conc(conc) => conc.toSmtTerm(guards, target),
+            Expr::Binary(bse) => bse.toSmtTerm(guards, target),
+            Expr::Unary(unary) => unary.toSmtTerm(guards, target),
+        }
+    }
+}
+
+impl<T> Smt for UnarySymExpr<T>
+where
+    T: Smt,
+{
+    fn toSmtTerm(&self, guards: &mut Vec<String>, target: &TargetInfo) -> Option<Term> {
+        let operand = self.getOperand().toSmtTerm(guards, target)?;
+        let promoted = operand.cast(operand.ty.promote(), target);
+        match self.getOpcode() {
+            UnaryOpcode::Neg => Some(Term::new(format!("(bvneg {})", promoted.text), promoted.ty)),
+            UnaryOpcode::Not => Some(Term::new(format!("(bvnot {})", promoted.text), promoted.ty)),
+            UnaryOpcode::LNot => Some(Term::fromBool(
+                format!("(not {})", operand.isTrue(target)),
+                target,
+            )),
+            UnaryOpcode::Cast(ty) if ty.isInteger() => Some(operand.cast(ty, target)),
+            UnaryOpcode::Cast(_) => None,
+        }
+    }
+}
+
+impl<T1, T2> Smt for BinarySymExpr<T1, T2>
+where
+    T1: Smt,
+    T2: Smt,
+{
+    fn toSmtTerm(&self, guards: &mut Vec<String>, target: &TargetInfo) -> Option<Term> {
+        let op = self.getOpcode();
+        let lhs = self.getLHS().toSmtTerm(guards, target)?;
+
+        // The RHS of a logical operator is only evaluated, and needs to be
+        // defined, when the LHS does not decide the result.
+        let mut rhsGuards = vec![];
+        let rhs = self.getRHS().toSmtTerm(&mut rhsGuards, target)?;
+        for guard in rhsGuards {
+            guards.push(match op {
+                Opcode::LAnd => format!("(=> {} {})", lhs.isTrue(target), guard),
+                Opcode::LOr => format!("(=> (not {}) {})", lhs.isTrue(target), guard),
+                _ => guard,
+            });
+        }
+
+        match op {
+            Opcode::LAnd => {
+                return Some(Term::fromBool(
+                    format!("(and {} {})", lhs.isTrue(target), rhs.isTrue(target)),
+                    target,
+                ))
+            }
+            Opcode::LOr => {
+                return Some(Term::fromBool(
+                    format!("(or {} {})", lhs.isTrue(target), rhs.isTrue(target)),
+                    target,
+                ))
+            }
+            Opcode::Assign => return Some(rhs.cast(lhs.ty, target)),
+            Opcode::Shl | Opcode::Shr => {
+                // Operands of shifts are promoted independently, and the
+                // result has the type of the promoted LHS.
+                let ty = lhs.ty.promote();
+                let amount = rhs.cast(rhs.ty.promote(), target);
+                let width = literal(ty.getWidth(target) as i128, amount.ty, target);
+                let inRange = if amount.ty.isSigned(target) {
+                    format!(
+                        "(and (bvsge {a} {}) (bvslt {a} {}))",
+                        literal(0, amount.ty, target),
+                        width,
+                        a = amount.text
+                    )
+                } else {
+                    format!("(bvult {} {})", amount.text, width)
+                };
+                guards.push(inRange);
+
+                let bvop = match (op, ty.isSigned(target)) {
+                    (Opcode::Shl, _) => "bvshl",
+                    (_, true) => "bvashr",
+                    (_, false) => "bvlshr",
+                };
+                let (l, r) = (lhs.cast(ty, target), amount.cast(ty, target));
+                return Some(Term::new(format!("({} {} {})", bvop, l.text, r.text), ty));
+            }
+            _ => {}
+        }
+
+        let ty = lhs.ty.getCommonType(rhs.ty, target);
+        let (l, r) = (lhs.cast(ty, target), rhs.cast(ty, target));
+        let signed = ty.isSigned(target);
+        let bvop = match op {
+            Opcode::Mul => "bvmul",
+            Opcode::Div if signed => "bvsdiv",
+            Opcode::Div => "bvudiv",
+            Opcode::Rem if signed => "bvsrem",
+            Opcode::Rem => "bvurem",
+            Opcode::Add => "bvadd",
+            Opcode::Sub => "bvsub",
+            Opcode::And => "bvand",
+            Opcode::Xor => "bvxor",
+            Opcode::Or => "bvor",
+            Opcode::LT if signed => "bvslt",
+            Opcode::LT => "bvult",
+            Opcode::GT if signed => "bvsgt",
+            Opcode::GT => "bvugt",
+            Opcode::LE if signed => "bvsle",
+            Opcode::LE => "bvule",
+            Opcode::GE if signed => "bvsge",
+            Opcode::GE => "bvuge",
+            Opcode::EQ => "=",
+            Opcode::NE => "distinct",
+            _ => unreachable!(),
+        };
+
+        let text = format!("({} {} {})", bvop, l.text, r.text);
+        match op {
+            Opcode::Div | Opcode::Rem => {
+                guards.push(r.isTrue(target));
+                Some(Term::new(text, ty))
+            }
+            op if op.isComparison() => Some(Term::fromBool(text, target)),
+            _ => Some(Term::new(text, ty)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toSmtTerm() {
+        let target = TargetInfo::default();
+        let int = CDataTypes::Int;
+        let s = Sym::new("s", "int");
+        let u = Sym::new("u", "unsigned int");
+        let sc = Sym::new("sc", "signed char");
+        let mut guards = vec![];
+
+        // -1 converts to UINT_MAX.
+        let lt = BinarySymExpr::new(&u, &Conc::new(-1, int), Opcode::LT);
+        assert_eq!(
+            lt.toSmtTerm(&mut guards, &target).unwrap().text,
+            "(ite (bvult u (_ bv4294967295 32)) (_ bv1 32) (_ bv0 32))"
+        );
+
+        let rem = BinarySymExpr::new(&sc, &s, Opcode::Rem);
+        let term = rem.toSmtTerm(&mut guards, &target).unwrap();
+        assert_eq!(term.text, "(bvsrem ((_ sign_extend 24) sc) s)");
+        assert_eq!(guards, vec!["(distinct s (_ bv0 32))"]);
+
+        // The divisor only needs to be non-zero if s is.
+        guards.clear();
+        let and = BinarySymExpr::new(&s, &rem, Opcode::LAnd);
+        and.toSmtTerm(&mut guards, &target).unwrap();
+        assert_eq!(
+            guards,
+            vec!["(=> (distinct s (_ bv0 32)) (distinct s (_ bv0 32)))"]
+        );
+
+        guards.clear();
+        let cast = UnarySymExpr::new(&s, UnaryOpcode::Cast(CDataTypes::UnsignedChar));
+        let shl = BinarySymExpr::new(&cast, &u, Opcode::Shl);
+        assert_eq!(
+            shl.toSmtTerm(&mut guards, &target).unwrap().text,
+            "(bvshl ((_ zero_extend 24) ((_ extract 7 0) s)) u)"
+        );
+        assert_eq!(guards, vec!["(bvult u (_ bv32 32))"]);
+
+        let f = UnarySymExpr::new(&s, UnaryOpcode::Cast(CDataTypes::Float));
+        assert!(f.toSmtTerm(&mut guards, &target).is_none());
+    }
+}
@@ -0,0 +1,644 @@
+//! Strategy : Pluggable ways of generating corner case tests.
+//!
+//! A CornerCaseStrategy turns a pair of symbols and an operator into tests of
+//! `S1 Op S2`. The built-in strategies are kept in a Registry, to which users
+//! can add their own, e.g. for a specific checker, and pick from by name at
+//! run time.
+//!
+//! # Examples
+//! ```
+//! use symi::BinOp::Opcode;
+//! use symi::Strategy::Registry;
+//! use symi::Symbol::Sym;
+//! use symi::Target::TargetInfo;
+//!
+//! let registry = Registry::new();
+//! let x = Sym::new("x", "int");
+//! let y = Sym::new("y", "unsigned int");
+//! let target = TargetInfo::default();
+//! let boundary = registry.get("boundary").unwrap();
+//! for test in boundary.generate(&x, &y, Opcode::LT, &target) {
+//!     println!("{test}");
+//! }
+//! ```
+
+use crate::BinOp::Opcode;
+use crate::Corner::{self, CornerTest, Location};
+use crate::Expr::{BinarySymExpr, Expr, ToExpr};
+use crate::Generator::{Generator, Weights};
+use crate::Oracle;
+use crate::Symbol::Sym;
+use crate::Target::TargetInfo;
+use crate::Test::Test;
+use crate::UnOp::UnaryOpcode;
+use std::sync::Arc;
+
+/// Iterator over the tests of a strategy.
+pub type Tests = Box<dyn Iterator<Item = Arc<CornerTest>>>;
+
+pub trait CornerCaseStrategy {
+    /// Name the strategy is picked by.
+    fn getName(&self) -> &str;
+
+    /// Returns the tests of `S1 Op S2` on `target`.
+    fn generate(&self, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo) -> Tests;
+}
+
+/// Windows of three values at the limits of both types and around 0. Every
+/// window of S1 is paired with every window of S2.
+///
+/// The limits of each type are also looked at inside the other type, since the
+/// usual arithmetic conversions change the meaning of values around them. For
+/// example int and unsigned int meet in unsigned int, so [-1, 1] of int becomes
+/// {UINT_MAX, 0, 1}.
+pub struct Boundary;
+
+/// The corner case matrix of Corner::cases around the middle of the values of
+/// both types.
+pub struct MidRange;
+
+/// The corner case matrix of Corner::cases around the minimum and maximum of
+/// the values of both types.
+pub struct Extremes;
+
+/// The corner case matrix of Corner::cases at every location, for the ways
+/// the windows of S1 and S2 relate in `overlaps`.
+pub struct Overlap {
+    pub overlaps: Vec<Corner::Overlap>,
+}
+
+/// The windows of Boundary, with either symbol explicitly cast to the type of
+/// the other one, `(t2)S1 Op S2` and `S1 Op (t1)S2`. Pairs of the same type
+/// have no tests.
+pub struct Cast;
+
+/// Windows of random width and position inside each type. The same seed gives
+/// the same windows for the same symbols and operator, and others for others.
+pub struct Random {
+    pub seed: u64,
+    /// Number of tests per pair of symbols.
+    pub count: usize,
+}
+
+/// Random expressions of both symbols, see Generator. The Conditional is a
+/// random operation, and the Assertion compares two random expressions with
+/// Op. Only the tests the oracle decides over all assignments are kept, as
+/// sampling is no proof for expressions that far from linear: pairs of narrow
+/// symbols, or Conditionals narrowing them enough.
+pub struct Deep {
+    pub seed: u64,
+    /// Number of tests tried per pair of symbols.
+    pub count: usize,
+    /// Most operations from the root of an expression to a leaf.
+    pub depth: usize,
+    pub weights: Weights,
+}
+
+impl Boundary {
+    /// Windows [lo, lo + 2] of values of S to pair with values of Other. They
+    /// are centered on the limits of S's type, on 0, and on the limits of
+    /// Other's type which S can hold, and are shifted to stay inside S's type.
+    pub fn getWindows(S: &Sym, Other: &Sym, target: &TargetInfo) -> Vec<(i128, i128)> {
+        let (T_MIN, T_MAX) = S.getTypeRange(target);
+        let (O_MIN, O_MAX) = Other.getTypeRange(target);
+
+        let mut pivots = vec![T_MIN, 0, T_MAX];
+        pivots.extend([O_MIN, O_MAX].iter().filter(|p| T_MIN < **p && **p < T_MAX));
+
+        let mut windows: Vec<(i128, i128)> = pivots
+            .into_iter()
+            .map(|p| {
+                let hi = (p.max(T_MIN + 1) + 1).min(T_MAX);
+                (hi - 2, hi)
+            })
+            .collect();
+        windows.sort_unstable();
+        windows.dedup();
+        windows
+    }
+
+    /// Every window of S1 paired with every window of S2, see getWindows.
+    pub fn getWindowPairs(
+        S1: &Sym,
+        S2: &Sym,
+        target: &TargetInfo,
+    ) -> Vec<((i128, i128), (i128, i128))> {
+        let RHS = Boundary::getWindows(S2, S1, target);
+        Boundary::getWindows(S1, S2, target)
+            .into_iter()
+            .flat_map(|LHS| RHS.iter().map(move |RHS| (LHS, *RHS)))
+            .collect()
+    }
+}
+
+impl CornerCaseStrategy for Boundary {
+    fn getName(&self) -> &str {
+        "boundary"
+    }
+
+    fn generate(&self, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo) -> Tests {
+        let pairs = Boundary::getWindowPairs(S1, S2, target);
+        let (S1, S2, target) = (Arc::clone(S1), Arc::clone(S2), target.clone());
+        Box::new(
+            pairs
+                .into_iter()
+                .map(move |(LHS, RHS)| windowTest(&S1, LHS, &S2, RHS, Op, &target)),
+        )
+    }
+}
+
+impl CornerCaseStrategy for Cast {
+    fn getName(&self) -> &str {
+        "cast"
+    }
+
+    fn generate(&self, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo) -> Tests {
+        if S1.isa(S2.getType()) {
+            return Box::new(std::iter::empty());
+        }
+
+        let pairs = Boundary::getWindowPairs(S1, S2, target);
+        let (S1, S2, target) = (Arc::clone(S1), Arc::clone(S2), target.clone());
+        let (E1, E2) = (S1.toExpr(), S2.toExpr());
+        let casts = [
+            BinarySymExpr::new(&Expr::unary(&E1, UnaryOpcode::Cast(S2.getType())), &E2, Op),
+            BinarySymExpr::new(&E1, &Expr::unary(&E2, UnaryOpcode::Cast(S1.getType())), Op),
+        ];
+        Box::new(pairs.into_iter().flat_map(move |(LHS, RHS)| {
+            let Conditional = Corner::conditional(&S1, LHS, &S2, RHS, &target);
+            let target = target.clone();
+            casts
+                .clone()
+                .into_iter()
+                .map(move |Assertion| Test::new(&Conditional, &Assertion, &target))
+        }))
+    }
+}
+
+impl CornerCaseStrategy for MidRange {
+    fn getName(&self) -> &str {
+        "mid-range"
+    }
+
+    fn generate(&self, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo) -> Tests {
+        matrix(S1, S2, Op, target, |case| case.location == Location::Mid)
+    }
+}
+
+impl CornerCaseStrategy for Extremes {
+    fn getName(&self) -> &str {
+        "extremes"
+    }
+
+    fn generate(&self, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo) -> Tests {
+        matrix(S1, S2, Op, target, |case| case.location != Location::Mid)
+    }
+}
+
+impl CornerCaseStrategy for Overlap {
+    fn getName(&self) -> &str {
+        "overlap"
+    }
+
+    fn generate(&self, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo) -> Tests {
+        matrix(S1, S2, Op, target, |case| {
+            self.overlaps.contains(&case.overlap)
+        })
+    }
+}
+
+impl CornerCaseStrategy for Random {
+    fn getName(&self) -> &str {
+        "random"
+    }
+
+    fn generate(&self, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo) -> Tests {
+        // Pairs of the same types, and operators, get windows of their own.
+        let key = format!(
+            "{} {:?} {} {:?} {}",
+            S1.name,
+            S1.getType(),
+            S2.name,
+            S2.getType(),
+            Op
+        );
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        let mut rng = SplitMix64(self.seed ^ hash);
+        let mut pick = |S: &Sym| {
+            let (T_MIN, T_MAX) = S.getTypeRange(target);
+            let width = 1 + (rng.next() % 8) as i128;
+            let span = (T_MAX - T_MIN + 1) as u128;
+            let lo = T_MIN + ((((rng.next() as u128) << 64) | rng.next() as u128) % span) as i128;
+            let lo = lo.min(T_MAX - width + 1);
+            (lo, lo + width - 1)
+        };
+
+        let pairs: Vec<_> = (0..self.count).map(|_| (pick(S1), pick(S2))).collect();
+        let (S1, S2, target) = (Arc::clone(S1), Arc::clone(S2), target.clone());
+        Box::new(
+            pairs
+                .into_iter()
+                .map(move |(LHS, RHS)| windowTest(&S1, LHS, &S2, RHS, Op, &target)),
+        )
+    }
+}
+
+impl CornerCaseStrategy for Deep {
+    fn getName(&self) -> &str {
+        "deep"
+    }
+
+    fn generate(&self, S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo) -> Tests {
+        let symbols = [Arc::clone(S1), Arc::clone(S2)];
+        let mut gen = Generator::new(
+            self.seed,
+            &symbols,
+            self.depth,
+            self.weights.clone(),
+            target,
+        );
+        let tests: Vec<Arc<CornerTest>> = (0..self.count)
+            .filter_map(|_| {
+                let Conditional = gen.binary();
+                let (LHS, RHS) = (gen.expr(), gen.expr());
+                let Assertion = BinarySymExpr::new(&LHS, &RHS, Op);
+                Oracle::isExact(Conditional.as_ref(), Assertion.as_ref(), target)
+                    .then(|| Test::new(&Conditional, &Assertion, target))
+            })
+            .collect();
+        Box::new(tests.into_iter())
+    }
+}
+
+impl Default for Deep {
+    fn default() -> Self {
+        Deep {
+            seed: 0x5eed,
+            count: 4,
+            depth: 3,
+            weights: Weights::default(),
+        }
+    }
+}
+
+impl Default for Overlap {
+    /// Windows which share values.
+    fn default() -> Self {
+        Overlap {
+            overlaps: vec![Corner::Overlap::Partial, Corner::Overlap::Complete],
+        }
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random {
+            seed: 0x5eed,
+            count: 8,
+        }
+    }
+}
+
+/// Set of strategies to pick from by name.
+pub struct Registry {
+    strategies: Vec<Box<dyn CornerCaseStrategy>>,
+}
+
+impl Registry {
+    /// Returns a registry of the built-in strategies.
+    pub fn new() -> Registry {
+        let mut registry = Registry { strategies: vec![] };
+        registry.register(Box::new(Boundary));
+        registry.register(Box::new(MidRange));
+        registry.register(Box::new(Overlap::default()));
+        registry.register(Box::new(Extremes));
+        registry.register(Box::new(Cast));
+        registry.register(Box::new(Random::default()));
+        registry.register(Box::new(Deep::default()));
+        registry
+    }
+
+    /// Add a strategy, replacing any strategy of the same name.
+    pub fn register(&mut self, strategy: Box<dyn CornerCaseStrategy>) {
+        self.strategies
+            .retain(|other| other.getName() != strategy.getName());
+        self.strategies.push(strategy);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn CornerCaseStrategy> {
+        self.strategies
+            .iter()
+            .find(|strategy| strategy.getName() == name)
+            .map(|strategy| strategy.as_ref())
+    }
+
+    /// Names of the registered strategies, in order of registration.
+    pub fn getNames(&self) -> Vec<&str> {
+        self.strategies
+            .iter()
+            .map(|strategy| strategy.getName())
+            .collect()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::new()
+    }
+}
+
+/// Test of `S1 Op S2` with S1 in LHS and S2 in RHS.
+fn windowTest(
+    S1: &Arc<Sym>,
+    LHS: (i128, i128),
+    S2: &Arc<Sym>,
+    RHS: (i128, i128),
+    Op: Opcode,
+    target: &TargetInfo,
+) -> Arc<CornerTest> {
+    let Assertion = BinarySymExpr::new(&S1.toExpr(), &S2.toExpr(), Op);
+    let Conditional = Corner::conditional(S1, LHS, S2, RHS, target);
+    Test::new(&Conditional, &Assertion, target)
+}
+
+/// Tests of the cases of Corner::cases `keep` accepts.
+fn matrix<F>(S1: &Arc<Sym>, S2: &Arc<Sym>, Op: Opcode, target: &TargetInfo, keep: F) -> Tests
+where
+    F: Fn(&Corner::Case) -> bool,
+{
+    let cases: Vec<_> = Corner::cases(S1, S2, Op, target)
+        .into_iter()
+        .filter(|case| keep(case))
+        .collect();
+
+    let (S1, S2, target) = (Arc::clone(S1), Arc::clone(S2), target.clone());
+    Box::new(
+        cases
+            .into_iter()
+            .map(move |case| case.getTest(&S1, &S2, Op, &target)),
+    )
+}
+
+/// Merge tests with the same Conditional into one test checking all of their
+/// distinct assertions, e.g. the tests of every comparison operator for a pair
+/// of windows. Tests are kept in the order their Conditional first appears,
+/// and the verdicts of their assertions are kept as they are.
+pub fn merge<I>(tests: I) -> Vec<Arc<CornerTest>>
+where
+    I: IntoIterator<Item = Arc<CornerTest>>,
+{
+    let mut groups: Vec<(String, Arc<CornerTest>, Vec<String>, Vec<_>)> = vec![];
+    for test in tests {
+        let key = test.getConditional().to_string();
+        let index = match groups.iter().position(|(other, ..)| *other == key) {
+            Some(index) => index,
+            None => {
+                groups.push((key, Arc::clone(&test), vec![], vec![]));
+                groups.len() - 1
+            }
+        };
+
+        let (_, _, seen, assertions) = &mut groups[index];
+        for checked in test.getAssertions() {
+            let text = checked.0.to_string();
+            if !seen.contains(&text) {
+                seen.push(text);
+                assertions.push(checked.clone());
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(_, first, _, assertions)| first.withChecked(assertions))
+        .collect()
+}
+
+/// SplitMix64, small and good enough to place windows and shape random
+/// expressions.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_getWindows() {
+        let target = TargetInfo::default();
+        let s = Sym::new("s", "int");
+        let u = Sym::new("u", "unsigned int");
+        let uc = Sym::new("uc", "unsigned char");
+        let INT_MAX = i32::MAX as i128;
+        let UINT_MAX = u32::MAX as i128;
+
+        assert_eq!(
+            Boundary::getWindows(&s, &u, &target),
+            vec![
+                (-INT_MAX - 1, -INT_MAX + 1),
+                (-1, 1),
+                (INT_MAX - 2, INT_MAX)
+            ]
+        );
+        assert_eq!(
+            Boundary::getWindows(&u, &s, &target),
+            vec![(0, 2), (INT_MAX - 1, INT_MAX + 1), (UINT_MAX - 2, UINT_MAX)]
+        );
+        assert_eq!(
+            Boundary::getWindows(&s, &uc, &target),
+            vec![
+                (-INT_MAX - 1, -INT_MAX + 1),
+                (-1, 1),
+                (254, 256),
+                (INT_MAX - 2, INT_MAX)
+            ]
+        );
+
+        // A float holds the integers up to 2^24 without gaps.
+        let f = Sym::new("f", "float");
+        let FLT_INT = 1 << 24;
+        assert_eq!(
+            Boundary::getWindows(&f, &s, &target),
+            vec![(-FLT_INT, -FLT_INT + 2), (-1, 1), (FLT_INT - 2, FLT_INT)]
+        );
+        assert_eq!(Boundary::getWindows(&s, &f, &target).len(), 5);
+        let first = Boundary
+            .generate(&f, &s, Opcode::LT, &target)
+            .next()
+            .unwrap();
+        assert!(first.to_string().contains("f >= -16777216.0F"));
+    }
+
+    #[test]
+    fn test_Registry() {
+        let target = TargetInfo::default();
+        struct Nothing;
+        impl CornerCaseStrategy for Nothing {
+            fn getName(&self) -> &str {
+                "boundary"
+            }
+
+            fn generate(&self, _: &Arc<Sym>, _: &Arc<Sym>, _: Opcode, _: &TargetInfo) -> Tests {
+                Box::new(std::iter::empty())
+            }
+        }
+
+        let x = Sym::new("x", "int");
+        let y = Sym::new("y", "unsigned int");
+        let mut registry = Registry::new();
+        assert_eq!(
+            registry.getNames(),
+            vec![
+                "boundary",
+                "mid-range",
+                "overlap",
+                "extremes",
+                "cast",
+                "random",
+                "deep"
+            ]
+        );
+        assert_eq!(
+            registry
+                .get("boundary")
+                .unwrap()
+                .generate(&x, &y, Opcode::LT, &target)
+                .count(),
+            9
+        );
+
+        let casts: Vec<String> = registry
+            .get("cast")
+            .unwrap()
+            .generate(&x, &y, Opcode::LT, &target)
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(casts.len(), 18);
+        assert!(casts[0].contains("clang_analyzer_eval((unsigned int)x < y)"));
+        assert!(casts[1].contains("clang_analyzer_eval(x < (int)y)"));
+        let same = registry
+            .get("cast")
+            .unwrap()
+            .generate(&x, &x, Opcode::LT, &target);
+        assert_eq!(same.count(), 0);
+
+        registry.register(Box::new(Nothing));
+        assert_eq!(
+            registry.getNames(),
+            vec![
+                "mid-range",
+                "overlap",
+                "extremes",
+                "cast",
+                "random",
+                "deep",
+                "boundary"
+            ]
+        );
+        assert_eq!(
+            registry
+                .get("boundary")
+                .unwrap()
+                .generate(&x, &y, Opcode::LT, &target)
+                .count(),
+            0
+        );
+        assert!(registry.get("none").is_none());
+
+        // The same seed gives the same tests.
+        let random = registry.get("random").unwrap();
+        let first: Vec<String> = random
+            .generate(&x, &y, Opcode::LT, &target)
+            .map(|t| t.to_string())
+            .collect();
+        let again: Vec<String> = random
+            .generate(&x, &y, Opcode::LT, &target)
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(first.len(), 8);
+        assert_eq!(first, again);
+        // Other operators get other windows.
+        let conditionals = |Op| -> Vec<String> {
+            random
+                .generate(&x, &y, Op, &target)
+                .map(|t| t.getConditional().to_string())
+                .collect()
+        };
+        assert_ne!(conditionals(Opcode::LT), conditionals(Opcode::GT));
+
+        // The comparisons of two ints, at every location.
+        let z = Sym::new("z", "int");
+        let overlap = registry.get("overlap").unwrap();
+        assert_eq!(overlap.generate(&x, &z, Opcode::LT, &target).count(), 6);
+        let disjoint = Overlap {
+            overlaps: vec![Corner::Overlap::Disjoint],
+        };
+        let tests: Vec<String> = disjoint
+            .generate(&x, &z, Opcode::LT, &target)
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(tests.len(), 3);
+        assert!(tests.iter().all(|t| t.contains("TRUE")));
+
+        // All the assignments of two chars are tried.
+        let deep = registry.get("deep").unwrap();
+        let (c, s) = (Sym::new("c", "char"), Sym::new("s", "signed char"));
+        let first: Vec<String> = deep
+            .generate(&c, &s, Opcode::EQ, &target)
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(first.len(), 4);
+        assert_eq!(
+            first,
+            deep.generate(&c, &s, Opcode::EQ, &target)
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+        );
+        // Those of int and unsigned int only when the Conditional narrows them.
+        assert!(deep.generate(&x, &y, Opcode::EQ, &target).all(|test| {
+            let (Assertion, _) = &test.getAssertions()[0];
+            Oracle::isExact(test.getConditional().as_ref(), Assertion.as_ref(), &target)
+        }));
+    }
+
+    #[test]
+    fn test_merge() {
+        let target = TargetInfo::default();
+        let x = Sym::new("x", "int");
+        let y = Sym::new("y", "unsigned int");
+        let registry = Registry::new();
+        let ops = [Opcode::LT, Opcode::GE, Opcode::EQ];
+
+        let tests = || {
+            ["boundary", "extremes"].into_iter().flat_map(|name| {
+                let strategy = registry.get(name).unwrap();
+                ops.iter()
+                    .flat_map(|op| strategy.generate(&x, &y, *op, &target))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let merged = merge(tests());
+        assert!(merged.len() * ops.len() <= tests().count());
+        assert_eq!(merged[0].getAssertions().len(), 3);
+
+        // x: [-1, 1], y: [0, 2] in unsigned int.
+        let printed = merged[3].to_string();
+        assert!(printed.contains("if (x >= -1 && x <= 1 && y >= 0U && y <= 2U)"));
+        assert!(printed.contains(
+            "      clang_analyzer_eval(x < y); // expected-warning{{UNKNOWN}}
+      clang_analyzer_eval(x >= y); // expected-warning{{UNKNOWN}}
+      clang_analyzer_eval(x == y); // expected-warning{{UNKNOWN}}"
+        ));
+    }
+}
@@ -5,10 +5,10 @@
 //!
 //! Each API call will create a symbol and store it in the Symbol Table. The
 //! users will be returned a reference to this symbol. They can write a
-//! probabilistic model to randomnly fuzz this symbol again in a test case. If
-//! they want to create some complex expression using this already generated
-//! symbol, then they can call any respective complex expression generator (in
-//! symi) by this symbol reference. The complex expression generator
+//! probabilistic model to randomnly fuzz this symbol again in a test case, see
+//! Generator. If they want to create some complex expression using this
+//! already generated symbol, then they can call any respective complex
+//! expression generator (in symi) by this symbol reference. The complex expression generator
 //! (ChainedBSE is one of them) will use this reference, lookup the symbol
 //! table, and use Arc::clone to create a new expression value which will also
 //! own the original symbol. This newly generated complex expression
@@ -18,7 +18,10 @@
 use crate::BinOp::Opcode;
 use crate::DataType::CDataTypes;
 use crate::Draw::Draw;
+use crate::Eval::Value;
 use crate::Expr::BinarySymExpr;
+use crate::Range::RangeSet;
+use crate::Target::TargetInfo;
 use std::fmt;
 use std::sync::Arc;
 
@@ -26,11 +29,13 @@ use std::sync::Arc;
 pub struct Sym {
     pub ty: CDataTypes,
     pub name: String,
-    // TODO: we need a range member
+    /// Values the symbol is known to take. None if it can take any value of
+    /// its type.
+    pub range: Option<RangeSet>,
 }
 
 /// A binary expression with a symbol and a concrete value.
-type SimpleBSE = BinarySymExpr<Sym, Conc<i64>>;
+type SimpleBSE = BinarySymExpr<Sym, Conc>;
 
 impl Sym {
     /// Returns a new symbolic object with said C data type. If no type is
@@ -38,26 +43,46 @@ impl Sym {
     /// in C).
     #[inline]
     pub fn new(name: &str, dtype: &str) -> Arc<Self> {
+        let ty = CDataTypes::getType(dtype).unwrap_or(CDataTypes::Int);
         Arc::new(Sym {
             name: name.to_string(),
-            ty: CDataTypes::getType(dtype).unwrap_or(CDataTypes::Int),
+            ty,
+            range: None,
+        })
+    }
+
+    /// Returns a copy of this symbol restricted to values in `range`.
+    pub fn constrained(&self, range: &RangeSet) -> Arc<Self> {
+        Arc::new(Sym {
+            name: self.name.clone(),
+            ty: self.ty,
+            range: Some(range.clone()),
         })
     }
 
     pub fn isa(&self, ty: CDataTypes) -> bool {
-        return self.ty == ty;
+        self.ty == ty
     }
 
     pub fn getName(&self) -> &String {
-        return &self.name;
+        &self.name
     }
 
     pub fn getType(&self) -> CDataTypes {
-        return self.ty;
+        self.ty
+    }
+
+    pub fn getTypeRange(&self, target: &TargetInfo) -> (i128, i128) {
+        self.ty.getRange(target)
     }
 
-    pub fn getTypeRange(&self) -> (i64, i64) {
-        self.ty.getRange()
+    /// Values this symbol can take on `target`, the whole range of its type
+    /// unless it has been constrained.
+    pub fn getRangeSet(&self, target: &TargetInfo) -> RangeSet {
+        match &self.range {
+            Some(range) => range.clone(),
+            None => RangeSet::full(self.ty, target),
+        }
     }
 
     /// Construct a binary symbolic expression for symbol S, constrained from
@@ -67,26 +92,17 @@ impl Sym {
     /// ```text
     ///         pivot - away <= s <= pivot + away
     /// ```
-    pub fn getConstraintsAround<'a>(
+    pub fn getConstraintsAround(
         sym: &Arc<Sym>,
-        pivot: i64,
-        away: i64,
+        pivot: i128,
+        away: i128,
+        target: &TargetInfo,
     ) -> Arc<BinarySymExpr<SimpleBSE, SimpleBSE>> {
-        let (T_MIN, T_MAX) = sym.ty.getRange();
-
-        if T_MIN <= (pivot - away) && (pivot + away) <= T_MAX {
-            let conc_before = Conc::new(pivot - away, sym.ty);
-            let conc_after = Conc::new(pivot + away, sym.ty);
-            let lhs = BinarySymExpr::new(&sym, &conc_before, Opcode::GE);
-            let rhs = BinarySymExpr::new(&sym, &conc_after, Opcode::LE);
-            BinarySymExpr::new(&lhs, &rhs, Opcode::LAnd)
-        } else {
-            let conc_before = Conc::new(pivot - away, sym.ty);
-            let conc_after = Conc::new(pivot + away, sym.ty);
-            let lhs = BinarySymExpr::new(&sym, &conc_before, Opcode::GE);
-            let rhs = BinarySymExpr::new(&sym, &conc_after, Opcode::LE);
-            BinarySymExpr::new(&lhs, &rhs, Opcode::LAnd)
-        }
+        let conc_before = Conc::wrapping(pivot - away, sym.ty, target);
+        let conc_after = Conc::wrapping(pivot + away, sym.ty, target);
+        let lhs = BinarySymExpr::new(sym, &conc_before, Opcode::GE);
+        let rhs = BinarySymExpr::new(sym, &conc_after, Opcode::LE);
+        BinarySymExpr::new(&lhs, &rhs, Opcode::LAnd)
     }
 }
 
@@ -120,57 +136,129 @@ impl Draw for Sym {
     }
 }
 
-/// A concrete symbolic type.
-pub struct Conc<T> {
+/// A concrete value of a C arithmetic type. The value always fits in the type,
+/// and is printed as a C literal of exactly that type. Values of floating
+/// types are integers, see CDataTypes::getRange().
+pub struct Conc {
     pub ty: CDataTypes,
-    pub val: T,
+    pub val: i128,
 }
 
-impl<T> Conc<T> {
+impl Conc {
     // TODO: dtype can be of CDataTypes or str, similarly in other new()
     // methods.
-    pub fn new(val: T, ty: CDataTypes) -> Arc<Conc<T>> {
+    /// Returns a new concrete value.
+    ///
+    /// # Panics
+    /// If `val` does not fit in `ty` on every target, see getPortableRange().
+    /// Use tryNew() for values of a given target, or wrapping() to convert
+    /// them.
+    pub fn new<T: Into<i128>>(val: T, ty: CDataTypes) -> Arc<Conc> {
+        let val = val.into();
+        let (T_MIN, T_MAX) = ty.getPortableRange();
+        assert!(
+            T_MIN <= val && val <= T_MAX,
+            "{} is not a value of type {}",
+            val,
+            ty
+        );
         Arc::new(Conc { ty, val })
     }
 
-    // pub fn new(val: T, dtype: &str) -> Conc<T> {
-    //     Conc(Arc::new(ConcInner {
-    //         ty: CDataTypes::getType(dtype).unwrap_or(CDataTypes::Int),
-    //         val
-    //     }))
-    // }
+    /// Returns a new concrete value. If `val` does not fit in `ty` on
+    /// `target`, it is wrapped around or rounded the way a C conversion to
+    /// `ty` does.
+    pub fn wrapping<T: Into<i128>>(val: T, ty: CDataTypes, target: &TargetInfo) -> Arc<Conc> {
+        Arc::new(Conc {
+            ty,
+            val: ty.wrap(val.into(), target),
+        })
+    }
+
+    /// Returns a new concrete value, or None if `val` does not fit in `ty` on
+    /// `target`.
+    pub fn tryNew<T: Into<i128>>(val: T, ty: CDataTypes, target: &TargetInfo) -> Option<Arc<Conc>> {
+        let val = val.into();
+        if ty.getMin(target) <= val && val <= ty.getMax(target) {
+            Some(Arc::new(Conc { ty, val }))
+        } else {
+            None
+        }
+    }
+
+    pub fn getValue(&self) -> Value {
+        Value {
+            ty: self.ty,
+            val: self.val,
+        }
+    }
+
+    /// Spell the value as a C literal. Types narrower than int have no
+    /// literals of their own, their values are spelled as int literals since
+    /// they are promoted to int anyway. The minimum of a signed type cannot be
+    /// written as a negated literal, as the literal itself would not fit, so it
+    /// is spelled like limits.h does, e.g. `(-2147483647 - 1)`. Floating
+    /// values are spelled with a fraction, e.g. `2.0F`. The spelling is valid
+    /// on every target.
+    pub fn literal(&self) -> String {
+        let suffix = match self.ty {
+            CDataTypes::Float => "F",
+            CDataTypes::LongDouble => "L",
+            CDataTypes::UnsignedInt => "U",
+            CDataTypes::Long => "L",
+            CDataTypes::UnsignedLong => "UL",
+            CDataTypes::LongLong => "LL",
+            CDataTypes::UnsignedLongLong => "ULL",
+            _ => "",
+        };
+
+        // The minimum of long is the one of int or the one of long long,
+        // depending on the target.
+        let (INT_MIN, LLONG_MIN) = (i32::MIN as i128, i64::MIN as i128);
+        let isMin = match self.ty.promote() {
+            CDataTypes::Int => self.val == INT_MIN,
+            CDataTypes::Long => self.val == INT_MIN || self.val == LLONG_MIN,
+            CDataTypes::LongLong => self.val == LLONG_MIN,
+            _ => false,
+        };
+        if isMin {
+            format!("(-{}{} - 1)", -(self.val + 1), suffix)
+        } else if !self.ty.isInteger() {
+            format!("{}.0{}", self.val, suffix)
+        } else {
+            format!("{}{}", self.val, suffix)
+        }
+    }
 }
 
 // // TODO: Give logical operations.
-// impl<T> PartialEq for Conc<T> {
+// impl PartialEq for Conc {
 //     fn eq(&self, other: &Self) -> bool {
 //         self.0.val == other.0.val && self.0.ty == other.0.ty
 //     }
 // }
 
-impl<T> fmt::Display for Conc<T>
-where
-    T: fmt::Display,
-{
+impl fmt::Display for Conc {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.val)
+        write!(f, "{}", self.literal())
     }
 }
 
-impl<T> Draw for Conc<T>
-where
-    T: fmt::Display,
-{
+impl Draw for Conc {
     fn dump(&self, tabl: Option<usize>) -> String {
-        format!("{ws}{}\n", self.val, ws = " ".repeat(tabl.unwrap_or(0)))
+        format!(
+            "{ws}{}\n",
+            self.literal(),
+            ws = " ".repeat(tabl.unwrap_or(0))
+        )
     }
 
     fn declare(&self) -> String {
         // FIXME: There should not be a declare() method.
-        format!("{}", self.val)
+        self.literal()
     }
 
-    type OutputType = Conc<T>;
+    type OutputType = Conc;
     fn iter(&self) -> (bool, &Self::OutputType) {
         #[cfg(debug_assertions)]
         println!("Value: {}", self);
@@ -185,14 +273,88 @@ mod tests {
 
     #[test]
     fn test_Sym() {
+        let target = TargetInfo::default();
         let s = Sym::new("x", "unsigned int");
         assert!(s.name == "x" && s.ty == CDataTypes::UnsignedInt);
+        assert_eq!(s.getRangeSet(&target).getRanges(), &[(0, u32::MAX as i128)]);
+
+        let c = s.constrained(&RangeSet::new(1, 9));
+        assert!(c == s && c.getRangeSet(&target).to_string() == "[1, 9]");
+    }
+
+    #[test]
+    fn test_Conc() {
+        let target = TargetInfo::default();
+        let c = Conc::wrapping(-1, CDataTypes::UnsignedInt, &target);
+        assert_eq!(c.to_string(), "4294967295U");
+        assert!(Conc::tryNew(-1, CDataTypes::UnsignedInt, &target).is_none());
+        assert!(Conc::tryNew(255, CDataTypes::UnsignedChar, &target).is_some());
+        assert!(Conc::tryNew(200, CDataTypes::Char, &target).is_none());
+
+        assert_eq!(
+            Conc::new(i32::MIN, CDataTypes::Int).to_string(),
+            "(-2147483647 - 1)"
+        );
+        assert_eq!(
+            Conc::new(i64::MIN, CDataTypes::LongLong).to_string(),
+            "(-9223372036854775807LL - 1)"
+        );
+        assert_eq!(
+            Conc::new(u64::MAX, CDataTypes::UnsignedLongLong).to_string(),
+            "18446744073709551615ULL"
+        );
+        assert_eq!(
+            Conc::tryNew(i64::MIN, CDataTypes::Long, &target)
+                .unwrap()
+                .to_string(),
+            "(-9223372036854775807L - 1)"
+        );
+        assert_eq!(Conc::new(-5, CDataTypes::Long).to_string(), "-5L");
+        assert_eq!(Conc::new(-128, CDataTypes::SignedChar).to_string(), "-128");
+        assert_eq!(
+            Conc::wrapping(300, CDataTypes::UnsignedChar, &target).to_string(),
+            "44"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "300 is not a value of type unsigned char")]
+    fn test_ConcOutOfRange() {
+        Conc::new(300, CDataTypes::UnsignedChar);
+    }
+
+    #[test]
+    #[should_panic(expected = "200 is not a value of type char")]
+    fn test_ConcNotPortable() {
+        Conc::new(200, CDataTypes::Char);
+    }
+
+    #[test]
+    fn test_ConcFloat() {
+        let target = TargetInfo::default();
+        assert_eq!(Conc::new(-3, CDataTypes::Double).to_string(), "-3.0");
+        assert_eq!(
+            Conc::new(1 << 24, CDataTypes::Float).to_string(),
+            "16777216.0F"
+        );
+        // 2^24 + 1 is no float, it rounds to even.
+        assert_eq!(
+            Conc::wrapping((1 << 24) + 1, CDataTypes::Float, &target).to_string(),
+            "16777216.0F"
+        );
+        assert!(Conc::tryNew((1 << 24) + 1, CDataTypes::Float, &target).is_none());
+        assert_eq!(
+            Conc::tryNew(1u64 << 63, CDataTypes::LongDouble, &target)
+                .unwrap()
+                .to_string(),
+            "9223372036854775808.0L"
+        );
     }
 
     #[test]
     fn test_getConstraintsAround() {
         let s = Sym::new("x", "unsigned int");
-        let _b = Sym::getConstraintsAround(&s, 0, 1);
+        let _b = Sym::getConstraintsAround(&s, 0, 1, &TargetInfo::default());
         println!("{}", _b);
         println!("{}", s);
     }
@@ -0,0 +1,172 @@
+//! Target : Properties of the target the generated tests are analyzed for.
+//!
+//! The C standard leaves the width of `long`, the width of `long double` and
+//! the signedness of plain `char` to the implementation. They are fixed by the
+//! data model and ABI of the target triple, which the analyzer receives with
+//! `-triple`. Everything in symi which depends on them (type ranges,
+//! conversions and evaluation) takes the target explicitly. The default target
+//! is x86_64-unknown-linux-gnu.
+//!
+//! # Examples
+//! ```
+//! use symi::DataType::CDataTypes;
+//! use symi::Target::TargetInfo;
+//!
+//! let i386 = TargetInfo::new("i386-unknown-linux-gnu").unwrap();
+//! assert_eq!(CDataTypes::Long.getWidth(&i386), 32);
+//! ```
+
+use std::fmt;
+
+/// Widths of int, long and pointers.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DataModel {
+    /// int, long and pointers are 32 bits wide (i386, arm).
+    ILP32,
+    /// long and pointers are 64 bits wide (64-bit Unix).
+    LP64,
+    /// Only long long and pointers are 64 bits wide (64-bit Windows).
+    LLP64,
+}
+
+/// Representation of `long double`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LongDoubleFormat {
+    /// Same as double.
+    Double,
+    /// x87 80-bit extended precision.
+    X87,
+    /// IEEE quadruple precision.
+    Quad,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetInfo {
+    pub triple: String,
+    pub model: DataModel,
+    pub charIsSigned: bool,
+    pub longDouble: LongDoubleFormat,
+    /// Storage width of long double in bits.
+    pub longDoubleWidth: u32,
+}
+
+impl TargetInfo {
+    /// Returns the target described by `triple`, or None if its architecture
+    /// is unknown.
+    pub fn new(triple: &str) -> Option<TargetInfo> {
+        let arch = triple.split('-').next().unwrap_or("");
+        let windows = triple.contains("windows") || triple.contains("win32");
+        let darwin = triple.contains("darwin") || triple.contains("macos");
+
+        let is64 = match arch {
+            "x86_64" | "aarch64" | "arm64" | "powerpc64" | "powerpc64le" | "riscv64" | "s390x" => {
+                true
+            }
+            "i386" | "i486" | "i586" | "i686" | "arm" | "armv7" | "thumbv7" | "powerpc"
+            | "riscv32" => false,
+            _ => return None,
+        };
+        let model = match (is64, windows) {
+            (false, _) => DataModel::ILP32,
+            (true, false) => DataModel::LP64,
+            (true, true) => DataModel::LLP64,
+        };
+
+        // Plain char is unsigned in the ARM, PowerPC and s390x ABIs, except
+        // on Darwin and Windows.
+        let charIsSigned = windows
+            || darwin
+            || !(arch.starts_with("arm")
+                || arch.starts_with("thumb")
+                || arch.starts_with("aarch64")
+                || arch.starts_with("powerpc")
+                || arch == "s390x");
+
+        let (longDouble, longDoubleWidth) = match arch {
+            _ if windows || (darwin && arch != "x86_64") => (LongDoubleFormat::Double, 64),
+            "x86_64" => (LongDoubleFormat::X87, 128),
+            "i386" | "i486" | "i586" | "i686" => (LongDoubleFormat::X87, 96),
+            "aarch64" | "arm64" | "riscv64" | "s390x" => (LongDoubleFormat::Quad, 128),
+            _ => (LongDoubleFormat::Double, 64),
+        };
+
+        Some(TargetInfo {
+            triple: triple.to_string(),
+            model,
+            charIsSigned,
+            longDouble,
+            longDoubleWidth,
+        })
+    }
+
+    /// Width of `long` in bits.
+    pub fn getLongWidth(&self) -> u32 {
+        match self.model {
+            DataModel::LP64 => 64,
+            DataModel::ILP32 | DataModel::LLP64 => 32,
+        }
+    }
+}
+
+impl Default for TargetInfo {
+    fn default() -> Self {
+        TargetInfo::new("x86_64-unknown-linux-gnu").unwrap()
+    }
+}
+
+impl fmt::Display for TargetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.triple)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType::CDataTypes;
+
+    #[test]
+    fn test_TargetInfo() {
+        let linux = TargetInfo::default();
+        assert_eq!(linux.model, DataModel::LP64);
+        assert!(linux.charIsSigned);
+
+        let i386 = TargetInfo::new("i386-unknown-linux-gnu").unwrap();
+        assert_eq!((i386.model, i386.longDoubleWidth), (DataModel::ILP32, 96));
+
+        let win = TargetInfo::new("x86_64-pc-windows-msvc").unwrap();
+        assert_eq!(win.model, DataModel::LLP64);
+        assert_eq!(win.longDouble, LongDoubleFormat::Double);
+
+        let arm = TargetInfo::new("aarch64-unknown-linux-gnu").unwrap();
+        assert!(!arm.charIsSigned);
+        assert!(
+            TargetInfo::new("aarch64-apple-darwin")
+                .unwrap()
+                .charIsSigned
+        );
+        assert!(TargetInfo::new("m68k-unknown-linux").is_none());
+    }
+
+    #[test]
+    fn test_targetTypes() {
+        let linux = TargetInfo::default();
+        assert_eq!(CDataTypes::Long.getWidth(&linux), 64);
+        assert_eq!(CDataTypes::Char.getRange(&linux), (-128, 127));
+        assert_eq!(
+            CDataTypes::Long.getCommonType(CDataTypes::UnsignedInt, &linux),
+            CDataTypes::Long
+        );
+
+        let win = TargetInfo::new("x86_64-pc-windows-msvc").unwrap();
+        assert_eq!(CDataTypes::Long.getMax(&win), i32::MAX as i128);
+        assert_eq!(
+            CDataTypes::Long.getCommonType(CDataTypes::UnsignedInt, &win),
+            CDataTypes::UnsignedLong
+        );
+
+        let arm = TargetInfo::new("armv7-unknown-linux-gnueabihf").unwrap();
+        assert_eq!(CDataTypes::Char.getRange(&arm), (0, 255));
+        assert_eq!(CDataTypes::Char.promote(), CDataTypes::Int);
+    }
+}
@@ -2,20 +2,38 @@
 //! clang_analyzer_eval calls.
 
 use crate::Draw::Draw;
-use crate::Expr::BinarySymExpr;
+use crate::Eval::Eval;
+use crate::Expr::{BinarySymExpr, Expr};
+use crate::Oracle::{self, Expectation, Feasibility, Verdict};
+use crate::Print::{Parens, Print};
+use crate::Range::{Constrain, Constraints};
+use crate::Smt::Smt;
+use crate::Target::TargetInfo;
+use crate::Transfer::EvalRange;
+use crate::Undefined::{CheckUB, Hazard};
 use std::fmt;
 use std::sync::Arc;
 
 /// A type for denoting test cases. Each test case for two Sym x and y consists
 /// of:
 /// 1. A set of constraints involving x and y.
-/// 2. An assertion (the test). This is a simple BinarySymExpr.
+/// 2. Assertions (the tests), checked one after the other. Each is a simple
+///    BinarySymExpr.
+/// 3. The expected result of each assertion, worked out by the Oracle.
+///    Assertions whose result the Oracle cannot decide are left out, as no
+///    `-verify` directive would be right for them.
+/// 4. Ranges of x and y derived from the constraints.
+/// 5. Whether the constraints can be satisfied at all. The analyzer has to
+///    reach the body of a feasible test, and must not reach an infeasible one.
+/// 6. The target the test is checked for, which sets the widths of its types.
 ///
 /// # Examples:
 /// ```c
 /// if (x > 0 && x < 10 && y > 11 && y < 20) {
 ///     // x: [1, 9], y: [12, 19]
+///     clang_analyzer_warnIfReached(); // expected-warning{{REACHABLE}}
 ///     clang_analyzer_eval(x != y); // expected-warning{{TRUE}}
+///     clang_analyzer_eval(x > y); // expected-warning{{FALSE}}
 /// }
 /// ```
 pub struct Test<T1, T2, T3, T4>
@@ -25,11 +43,19 @@ where
     T3: Draw,
     T4: Draw,
 {
-    // TODO: We may need a WarningRange member for `expected-warning{{}}`.
     Conditional: Arc<BinarySymExpr<T1, T2>>,
-    Assertion: Arc<BinarySymExpr<T3, T4>>, // Concrete types involved?
+    Assertions: Vec<Checked<T3, T4>>,
+    Ranges: Constraints,
+    Feasible: Feasibility,
+    Target: TargetInfo,
 }
 
+/// An assertion of a Test and what the Oracle says about it.
+pub type Checked<T3, T4> = (Arc<BinarySymExpr<T3, T4>>, Verdict);
+
+/// A Test of expressions whose shape is only known at run time.
+pub type DynTest = Test<Expr, Expr, Expr, Expr>;
+
 impl<T1, T2, T3, T4> Test<T1, T2, T3, T4>
 where
     T1: Draw,
@@ -37,43 +63,336 @@ where
     T3: Draw,
     T4: Draw,
 {
+    /// Returns a new test case and computes the expected result of its
+    /// assertion on `target`.
     pub fn new(
         Conditional: &Arc<BinarySymExpr<T1, T2>>,
         Assertion: &Arc<BinarySymExpr<T3, T4>>,
-    ) -> Arc<Test<T1, T2, T3, T4>> {
+        target: &TargetInfo,
+    ) -> Arc<Test<T1, T2, T3, T4>>
+    where
+        T1: Constrain,
+        T2: Constrain,
+        T3: EvalRange,
+        T4: EvalRange,
+    {
+        Self::withAssertions(Conditional, &[Arc::clone(Assertion)], target)
+    }
+
+    /// Returns a new test case checking all of `Assertions` under one
+    /// Conditional, and computes the expected result of each. Those the
+    /// Oracle cannot decide are left out.
+    pub fn withAssertions(
+        Conditional: &Arc<BinarySymExpr<T1, T2>>,
+        Assertions: &[Arc<BinarySymExpr<T3, T4>>],
+        target: &TargetInfo,
+    ) -> Arc<Test<T1, T2, T3, T4>>
+    where
+        T1: Constrain,
+        T2: Constrain,
+        T3: EvalRange,
+        T4: EvalRange,
+    {
         Arc::new(Test {
             Conditional: Arc::clone(Conditional),
-            Assertion: Arc::clone(Assertion),
+            Assertions: Assertions
+                .iter()
+                .map(|Assertion| {
+                    let Expected = Oracle::expect(Conditional.as_ref(), Assertion.as_ref(), target);
+                    (Arc::clone(Assertion), Expected)
+                })
+                .filter(|(_, Expected)| *Expected != Verdict::Undecided)
+                .collect(),
+            Ranges: Conditional.constraints(target),
+            Feasible: Oracle::feasibility(Conditional.as_ref(), target),
+            Target: target.clone(),
+        })
+    }
+
+    /// Returns a test of the same Conditional checking `Assertions`, whose
+    /// verdicts were worked out under that Conditional, e.g. by other tests of
+    /// it. The Oracle is not asked again.
+    pub(crate) fn withChecked(
+        &self,
+        Assertions: Vec<Checked<T3, T4>>,
+    ) -> Arc<Test<T1, T2, T3, T4>> {
+        Arc::new(Test {
+            Conditional: Arc::clone(&self.Conditional),
+            Assertions,
+            Ranges: self.Ranges.clone(),
+            Feasible: self.Feasible,
+            Target: self.Target.clone(),
         })
     }
+
+    pub fn getConditional(&self) -> &Arc<BinarySymExpr<T1, T2>> {
+        &self.Conditional
+    }
+
+    /// Assertions in the order they are checked, with their expected results.
+    pub fn getAssertions(&self) -> &[Checked<T3, T4>] {
+        &self.Assertions
+    }
+
+    /// Expected result of the first assertion.
+    pub fn getExpectation(&self) -> Option<Expectation> {
+        self.Assertions
+            .first()
+            .and_then(|(_, Expected)| Expected.getExpectation())
+    }
+
+    pub fn getFeasibility(&self) -> Feasibility {
+        self.Feasible
+    }
+
+    /// Ranges of the symbols constrained by the Conditional.
+    pub fn getRanges(&self) -> &Constraints {
+        &self.Ranges
+    }
+
+    /// Target the expected results hold on.
+    pub fn getTarget(&self) -> &TargetInfo {
+        &self.Target
+    }
+
+    /// Whether the verdicts of the Assertions agree with the feasibility of
+    /// the Conditional. No satisfying assignment reaches an Unreached Assertion
+    /// of a feasible test, e.g. one undefined wherever the Conditional holds,
+    /// so the analyzer cannot be expected to report anything for it.
+    pub fn isConsistent(&self) -> bool {
+        match self.Feasible {
+            Feasibility::Feasible => self
+                .Assertions
+                .iter()
+                .all(|(_, Expected)| *Expected != Verdict::Unreached),
+            Feasibility::Infeasible => self
+                .Assertions
+                .iter()
+                .all(|(_, Expected)| *Expected == Verdict::Unreached),
+            Feasibility::Unknown => true,
+        }
+    }
 }
 
-impl<T1, T2, T3, T4> fmt::Display for Test<T1, T2, T3, T4>
+impl<T1, T2, T3, T4> Test<T1, T2, T3, T4>
 where
-    T1: fmt::Display + Draw,
-    T2: fmt::Display + Draw,
-    T3: fmt::Display + Draw,
-    T4: fmt::Display + Draw,
+    T1: fmt::Display + Draw + Smt,
+    T2: fmt::Display + Draw + Smt,
+    T3: fmt::Display + Draw + Smt,
+    T4: fmt::Display + Draw + Smt,
 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
+    /// Returns an SMT-LIB2 script checking whether the Conditional implies
+    /// each Assertion, or None if any involves a non-integer type.
+    ///
+    /// The script makes two queries per Assertion. The Assertion is TRUE if
+    /// the first one, the Conditional with the negated Assertion, is unsat. It
+    /// is FALSE if the second one, the Conditional with the Assertion, is
+    /// unsat. If both are unsat the Conditional itself is infeasible.
+    pub fn toSmt(&self) -> Option<String> {
+        let mut exprs = vec![self.Conditional.as_ref() as &dyn Eval];
+        exprs.extend(self.Assertions.iter().map(|(a, _)| a.as_ref() as &dyn Eval));
+        let mut script = crate::Smt::header(&exprs, &self.Target);
+
+        let mut guards = vec![];
+        let cond = self.Conditional.toSmtTerm(&mut guards, &self.Target)?;
+        script += &format!("; if {}\n", self.Conditional);
+        for guard in guards {
+            script += &format!("(assert {})\n", guard);
+        }
+        script += &format!("(assert {})\n", cond.isTrue(&self.Target));
+
+        for (Assertion, _) in &self.Assertions {
+            // Guards of an Assertion only hold where it is checked.
+            let mut guards = vec![];
+            let assert = Assertion.toSmtTerm(&mut guards, &self.Target)?;
+            let guards: String = guards.iter().map(|g| format!("(assert {})\n", g)).collect();
+            script += &format!("; clang_analyzer_eval{}\n", Assertion);
+            script += &format!(
+                "; unsat: TRUE\n(push 1)\n{}(assert (not {}))\n(check-sat)\n(pop 1)\n",
+                guards,
+                assert.isTrue(&self.Target)
+            );
+            script += &format!(
+                "; unsat: FALSE\n(push 1)\n{}(assert {})\n(check-sat)\n(pop 1)\n",
+                guards,
+                assert.isTrue(&self.Target)
+            );
+        }
+        Some(script)
+    }
+}
+
+/// Where the analyzer meets undefined behavior in a test, and the warning it
+/// reports.
+struct UBSite<'a> {
+    /// None for the Conditional, otherwise the index of the Assertion.
+    at: Option<usize>,
+    diag: &'a str,
+}
+
+impl<T1, T2, T3, T4> Test<T1, T2, T3, T4>
+where
+    T1: Draw + Print,
+    T2: Draw + Print,
+    T3: Draw + Print,
+    T4: Draw + Print,
+{
+    /// Returns the test as C, with the Conditional and the Assertions
+    /// parenthesized according to `parens`.
+    pub fn print(&self, parens: Parens) -> String {
+        self.render(parens, None)
+    }
+
+    /// Print the test, expecting the warning of a checker reporting UB where
+    /// `undefined` says instead of the result of the Assertion there. Nothing
+    /// after it is reached, the analyzer stops on the path.
+    fn render(&self, parens: Parens, undefined: Option<UBSite>) -> String {
+        let expect = |text: &dyn fmt::Display| format!(" // expected-warning{{{{{}}}}}", text);
+        let (ifWarning, feasible) = match undefined {
+            Some(UBSite { at: None, diag }) => (expect(&diag), Feasibility::Infeasible),
+            _ => (String::new(), self.Feasible),
+        };
+        let ranges = match self.Ranges.iter().next() {
+            Some(_) => format!("\n      // {}", self.Ranges),
+            None => String::new(),
+        };
+
+        let reached = match feasible {
+            Feasibility::Feasible => {
+                "\n      clang_analyzer_warnIfReached(); // expected-warning{{REACHABLE}}"
+            }
+            Feasibility::Infeasible => "\n      clang_analyzer_warnIfReached(); // no-warning",
+            Feasibility::Unknown => "",
+        };
+
+        let mut evals = String::new();
+        for (i, (Assertion, Expected)) in self.Assertions.iter().enumerate() {
+            let warning = match &undefined {
+                Some(UBSite { at: None, .. }) => String::new(),
+                Some(UBSite { at: Some(at), diag }) if i == *at => expect(diag),
+                Some(UBSite { at: Some(at), .. }) if i > *at => String::new(),
+                // An unreachable assertion produces no warning at all.
+                _ => Expected
+                    .getExpectation()
+                    .map(|expected| expect(&expected))
+                    .unwrap_or_default(),
+            };
+            evals += &format!(
+                "\n      clang_analyzer_eval({});{}",
+                Assertion.print(parens),
+                warning
+            );
+        }
+
+        format!(
             "
-  if {} {{
-      clang_analyzer_eval{};
+  if ({}) {{{}{}{}{}
   }}
   ",
-            self.Conditional, self.Assertion
+            self.Conditional.print(parens),
+            ifWarning,
+            ranges,
+            reached,
+            evals
         )
     }
 }
 
+impl<T1, T2, T3, T4> Test<T1, T2, T3, T4>
+where
+    T1: Draw + CheckUB,
+    T2: Draw + CheckUB,
+    T3: Draw + CheckUB,
+    T4: Draw + CheckUB,
+{
+    /// Operations of the Conditional and of each Assertion whose behavior may
+    /// be undefined. Those of the Assertions are only looked for within the
+    /// Ranges of the Conditional.
+    fn hazardsOf(&self) -> (Vec<Hazard>, Vec<Vec<Hazard>>) {
+        let mut cond = vec![];
+        self.Conditional
+            .hazards(&Constraints::new(), &mut cond, &self.Target);
+        let asserts = self
+            .Assertions
+            .iter()
+            .map(|(Assertion, _)| {
+                let mut out = vec![];
+                Assertion.hazards(&self.Ranges, &mut out, &self.Target);
+                out
+            })
+            .collect();
+        (cond, asserts)
+    }
+
+    /// Splits the test in two of the same Conditional, one checking the
+    /// Assertions without undefined behavior and one checking the others. All
+    /// of them go to the second one if the Conditional itself may be
+    /// undefined. A test left without Assertions is None.
+    pub fn partitionHazards(&self) -> (Option<Arc<Self>>, Option<Arc<Self>>) {
+        let (cond, asserts) = self.hazardsOf();
+        let (mut safe, mut hazardous) = (vec![], vec![]);
+        for (checked, hazards) in self.Assertions.iter().zip(asserts) {
+            if cond.is_empty() && hazards.is_empty() {
+                safe.push(checked.clone());
+            } else {
+                hazardous.push(checked.clone());
+            }
+        }
+        let test = |Assertions: Vec<Checked<T3, T4>>| {
+            (!Assertions.is_empty()).then(|| self.withChecked(Assertions))
+        };
+        (test(safe), test(hazardous))
+    }
+
+    /// Operations of the test whose behavior may be undefined, see Undefined.
+    pub fn getHazards(&self) -> Vec<Hazard> {
+        let (cond, asserts) = self.hazardsOf();
+        cond.into_iter()
+            .chain(asserts.into_iter().flatten())
+            .collect()
+    }
+
+    /// Returns the test as C, expecting the warning of the checker which
+    /// reports its undefined behavior. This is only possible if the first
+    /// undefined operation the analyzer meets always is, and a checker reports
+    /// it, otherwise None is returned. Tests without UB are printed as is.
+    pub fn printUB(&self, parens: Parens) -> Option<String> {
+        let (cond, asserts) = self.hazardsOf();
+        let firstAssert = asserts.iter().enumerate().find(|(_, h)| !h.is_empty());
+        let (at, first) = match (cond.first(), firstAssert) {
+            (None, None) => return Some(self.print(parens)),
+            (Some(first), _) => (None, first),
+            // The analyzer must reach the Assertion to report anything.
+            (None, Some(_)) if self.Feasible != Feasibility::Feasible => return None,
+            (None, Some((i, hazards))) => (Some(i), &hazards[0]),
+        };
+        if !first.always {
+            return None;
+        }
+        let diag = first.kind.getDiagnostic()?;
+        Some(self.render(parens, Some(UBSite { at, diag })))
+    }
+}
+
+/// Tests are printed without the parentheses compilers do not ask for.
+impl<T1, T2, T3, T4> fmt::Display for Test<T1, T2, T3, T4>
+where
+    T1: Draw + Print,
+    T2: Draw + Print,
+    T3: Draw + Print,
+    T4: Draw + Print,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.print(Parens::Warnings))
+    }
+}
+
 impl<T1, T2, T3, T4> Draw for Test<T1, T2, T3, T4>
 where
-    T1: Draw,
-    T2: Draw,
-    T3: Draw,
-    T4: Draw,
+    T1: Draw + Print,
+    T2: Draw + Print,
+    T3: Draw + Print,
+    T4: Draw + Print,
 {
     // TODO:
     fn dump(&self, _tabl: Option<usize>) -> String {
@@ -83,7 +402,7 @@ where
     }
 
     fn declare(&self) -> String {
-        format!("")
+        String::new()
     }
 
     type OutputType = Test<T1, T2, T3, T4>;
@@ -95,17 +414,130 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testutil::Fixture;
+    use crate::DataType::CDataTypes;
     use crate::Symbol;
 
     #[test]
     fn test_TestExpr() {
+        let target = TargetInfo::default();
         let s = Symbol::Sym::new("x", "int");
-        let c = Symbol::Sym::getConstraintsAround(&s, 0, 1);
-        let a = Symbol::Sym::getConstraintsAround(&s, 1, 2);
+        let c = Symbol::Sym::getConstraintsAround(&s, 0, 1, &target);
+        let a = Symbol::Sym::getConstraintsAround(&s, 1, 2, &target);
 
-        let _t = Test::new(&c, &a);
+        let _t = Test::new(&c, &a, &target);
         println!("Test = {}", _t);
+        assert_eq!(_t.getExpectation(), Some(Expectation::True));
+        assert_eq!(_t.getRanges().to_string(), "x: [-1, 1]");
+        assert!(_t
+            .to_string()
+            .contains("clang_analyzer_eval(x >= -1 && x <= 3); // expected-warning{{TRUE}}"));
+        assert_eq!(_t.getFeasibility(), Feasibility::Feasible);
+        assert!(_t.to_string().contains("// expected-warning{{REACHABLE}}"));
+
+        let smt = _t.toSmt().unwrap();
+        assert!(smt.contains("(declare-const x (_ BitVec 32))"));
+        assert!(smt.contains("(assert (not (distinct (ite (and (distinct (ite (bvsge x "));
         println!("{s}");
         println!("{c}");
     }
+
+    #[test]
+    fn test_isConsistent() {
+        let fixture = Fixture::new(&[("x", CDataTypes::Int)]);
+        let test = |cond: &str, assert: &str| fixture.test(cond, assert);
+
+        assert!(test("x >= 0 && x <= 3", "x < 4").isConsistent());
+        assert!(test("x > 3 && x < 2", "x < 4").isConsistent());
+
+        // The division is undefined wherever the Conditional holds.
+        let undefined = test("x >= 0 && x <= 3", "10 / (x - x) > 0");
+        assert_eq!(undefined.getFeasibility(), Feasibility::Feasible);
+        assert_eq!(undefined.getAssertions()[0].1, Verdict::Unreached);
+        assert!(!undefined.isConsistent());
+    }
+
+    #[test]
+    fn test_partitionHazards() {
+        let fixture = Fixture::new(&[("x", CDataTypes::Int)]);
+        let asserts: Vec<_> = ["x > 0", "10 / (x - 1) > 0", "x < 5"]
+            .iter()
+            .map(|src| fixture.binary(src))
+            .collect();
+
+        let test = Test::withAssertions(
+            &fixture.binary("x >= 0 && x <= 3"),
+            &asserts,
+            &fixture.target,
+        );
+        let (safe, hazardous) = test.partitionHazards();
+        let (safe, hazardous) = (safe.unwrap(), hazardous.unwrap());
+        let show = |checked: &Checked<Expr, Expr>| format!("{} {:?}", checked.0, checked.1);
+        assert_eq!(safe.getAssertions().len(), 2);
+        assert_eq!(
+            show(&safe.getAssertions()[0]),
+            show(&test.getAssertions()[0])
+        );
+        assert_eq!(
+            show(&safe.getAssertions()[1]),
+            show(&test.getAssertions()[2])
+        );
+        assert_eq!(
+            show(&hazardous.getAssertions()[0]),
+            show(&test.getAssertions()[1])
+        );
+        assert_eq!(safe.getFeasibility(), test.getFeasibility());
+
+        let test = Test::withAssertions(
+            &fixture.binary("x >= -3 && x <= 3 && 10 / x > 1"),
+            &asserts[..1],
+            &fixture.target,
+        );
+        let (safe, hazardous) = test.partitionHazards();
+        assert!(safe.is_none());
+        assert_eq!(hazardous.unwrap().getAssertions().len(), 1);
+    }
+
+    #[test]
+    fn test_printUB() {
+        let fixture = Fixture::new(&[("x", CDataTypes::Int)]);
+        let test = |cond: &str, assert: &str| fixture.test(cond, assert);
+
+        let safe = test("x >= 0 && x <= 3", "10 / (x - 5) < 0");
+        assert!(safe.getHazards().is_empty());
+        assert_eq!(safe.printUB(Parens::Warnings), Some(safe.to_string()));
+
+        let maybe = test("x >= -1 && x <= 1", "10 / x > 0");
+        assert_eq!(
+            maybe.getHazards()[0].to_string(),
+            "10 / x: division by zero (maybe)"
+        );
+        assert_eq!(maybe.printUB(Parens::Warnings), None);
+
+        let shift = test("x >= 32 && x <= 40", "1 << x > 0").printUB(Parens::Warnings);
+        assert!(shift
+            .unwrap()
+            .contains("clang_analyzer_eval(1 << x > 0); // expected-warning{{shift}}"));
+
+        let div = test("x == 0 && 10 / x > 1", "x == 0").printUB(Parens::Warnings);
+        let div = div.unwrap();
+        assert!(div.contains("if (x == 0 && 10 / x > 1) { // expected-warning{{Division by zero}}"));
+        assert!(div.contains("clang_analyzer_warnIfReached(); // no-warning"));
+        assert!(div.contains("clang_analyzer_eval(x == 0);\n"));
+
+        // Nothing is checked after the analyzer stops.
+        let cond = fixture.binary("x >= 32 && x <= 40");
+        let asserts: Vec<_> = ["x > 0", "1 << x > 0", "x == 0"]
+            .iter()
+            .map(|src| fixture.binary(src))
+            .collect();
+        let shifts = Test::withAssertions(&cond, &asserts, &fixture.target);
+        assert_eq!(shifts.getExpectation(), Some(Expectation::True));
+        assert!(shifts.printUB(Parens::Warnings).unwrap().contains(
+            "      clang_analyzer_eval(x > 0); // expected-warning{{TRUE}}
+      clang_analyzer_eval(1 << x > 0); // expected-warning{{shift}}
+      clang_analyzer_eval(x == 0);
+  }"
+        ));
+    }
 }
@@ -0,0 +1,397 @@
+//! TestFile : Build the C file the tests are written to.
+//!
+//! A TestFile is a list of TestFunctions after the declarations of the
+//! analyzer's debug functions. Each Test goes in a function of its own, which
+//! takes only the symbols it uses as parameters. The analyzer starts every
+//! function with fresh values for its parameters, so the constraints of one
+//! Conditional never leak into the next test as they would within a single
+//! function.
+//!
+//! The file is written as a lit test for `clang/test/Analysis`: RUN lines
+//! checking it with -verify come first, one per set of analyzer-config options
+//! it is run with, and the expected results are -verify directives.
+//!
+//! # Examples
+//! ```
+//! use symi::BinOp::Opcode;
+//! use symi::DataType::CDataTypes;
+//! use symi::Expr::BinarySymExpr;
+//! use symi::Symbol::{Conc, Sym};
+//! use symi::Target::TargetInfo;
+//! use symi::Test::Test;
+//! use symi::TestFile::{TestFile, TestFunction};
+//!
+//! let (x, y) = (Sym::new("x", "int"), Sym::new("y", "unsigned char"));
+//! let cond = BinarySymExpr::new(&x, &Conc::new(10, CDataTypes::Int), Opcode::LT);
+//! let assert = BinarySymExpr::new(&x, &Conc::new(20, CDataTypes::Int), Opcode::LT);
+//!
+//! let target = TargetInfo::default();
+//! let test = Test::new(&cond, &assert, &target);
+//! let mut file = TestFile::new("test", &target);
+//! file.addTest(&test, test.to_string());
+//! file.addFunction(TestFunction::new("bar").returns("int").param(&x).param(&y));
+//!
+//! let text = file.to_string();
+//! assert!(text.contains("void test_0(int x) {"));
+//! assert!(text.contains("int bar(int x, unsigned char y) {\n  return 0;\n}"));
+//! assert!(text.starts_with("// RUN: %clang_analyze_cc1 -triple "));
+//! ```
+
+use crate::DataType::CDataTypes;
+use crate::Draw::Draw;
+use crate::Eval::{Eval, Leaf};
+use crate::Symbol::Sym;
+use crate::Target::TargetInfo;
+use crate::Test::Test;
+use std::fmt;
+
+/// Checkers every RUN line enables, for the analyzer's debug functions and
+/// the warnings of undefined behavior.
+const CHECKERS: &str = "core,debug.ExprInspection";
+
+/// Options every RUN line passes. The Oracle expects UNKNOWN where the result
+/// is not decided, which the analyzer only says without eager assumptions.
+const CONFIGS: [(&str, &str); 1] = [("eagerly-assume", "false")];
+
+/// Where -verify directives are written.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Directives {
+    /// At the end of the line the diagnostic is expected on.
+    Inline,
+    /// On a line of their own before it, as `expected-warning@+1{{...}}`.
+    Above,
+    /// Nowhere, for tests whose results are unspecified. The file is not run
+    /// with -verify, it only checks that the analyzer does not crash.
+    Omitted,
+}
+
+/// Move each directive at the end of a line of `text` above it.
+fn placeAbove(text: &str) -> String {
+    let mut out = vec![];
+    for line in text.lines() {
+        let Some(at) = line.find("// expected-") else {
+            out.push(line.to_string());
+            continue;
+        };
+        let (code, directive) = (line[..at].trim_end(), &line[at + 3..]);
+        let indent = &code[..code.len() - code.trim_start().len()];
+        match directive.find("{{") {
+            Some(open) => {
+                out.push(format!(
+                    "{}// {}@+1{}",
+                    indent,
+                    &directive[..open],
+                    &directive[open..]
+                ));
+                out.push(code.to_string());
+            }
+            None => out.push(line.to_string()),
+        }
+    }
+    out.join("\n")
+}
+
+/// Remove the directive at the end of each line of `text`.
+fn stripDirectives(text: &str) -> String {
+    text.lines()
+        .map(|line| match line.find(" // expected-") {
+            Some(at) => &line[..at],
+            None => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A C function definition, built up a piece at a time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestFunction {
+    name: String,
+    /// Return type, "void" unless set.
+    ret: String,
+    params: Vec<(CDataTypes, String)>,
+    /// Statements of the body, as C.
+    body: Vec<String>,
+}
+
+impl TestFunction {
+    pub fn new(name: &str) -> TestFunction {
+        TestFunction {
+            name: name.to_string(),
+            ret: "void".to_string(),
+            params: vec![],
+            body: vec![],
+        }
+    }
+
+    /// Set the return type. A function returning a value returns 0.
+    pub fn returns(mut self, ty: &str) -> TestFunction {
+        self.ret = ty.to_string();
+        self
+    }
+
+    /// Add `sym` to the parameters, unless a parameter of that name exists.
+    pub fn param(mut self, sym: &Sym) -> TestFunction {
+        if !self.params.iter().any(|(_, name)| *name == sym.name) {
+            self.params.push((sym.ty, sym.name.clone()));
+        }
+        self
+    }
+
+    /// Add all symbols of `expr` to the parameters, in order of appearance.
+    pub fn uses(mut self, expr: &dyn Eval, target: &TargetInfo) -> TestFunction {
+        let mut leaves = vec![];
+        expr.leaves(&mut leaves, target);
+        for leaf in leaves {
+            if let Leaf::Sym(sym) = leaf {
+                self = self.param(sym);
+            }
+        }
+        self
+    }
+
+    /// Append C code to the body.
+    pub fn statement(mut self, text: String) -> TestFunction {
+        self.body.push(text);
+        self
+    }
+
+    pub fn getName(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for TestFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|(ty, name)| format!("{} {}", ty, name))
+            .collect();
+        let params = if params.is_empty() {
+            "void".to_string()
+        } else {
+            params.join(", ")
+        };
+
+        write!(f, "{} {}({}) {{", self.ret, self.name, params)?;
+        write!(f, "{}", self.body.concat().trim_end())?;
+        if self.ret != "void" {
+            write!(f, "\n  return 0;")?;
+        }
+        write!(f, "\n}}")
+    }
+}
+
+/// A C file of tests for the analyzer, with their expectations for `target`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestFile {
+    target: TargetInfo,
+    /// analyzer-config options of each RUN line, besides CONFIGS.
+    runs: Vec<Vec<(String, String)>>,
+    directives: Directives,
+    /// Tests go in functions named `<prefix>_<n>`.
+    prefix: String,
+    functions: Vec<TestFunction>,
+}
+
+impl TestFile {
+    /// Returns an empty file for `target`.
+    pub fn new(prefix: &str, target: &TargetInfo) -> TestFile {
+        TestFile {
+            target: target.clone(),
+            runs: vec![],
+            directives: Directives::Inline,
+            prefix: prefix.to_string(),
+            functions: vec![],
+        }
+    }
+
+    /// Add a RUN line with the analyzer-config options `configs`. Without any,
+    /// the file has a single RUN line with the options of CONFIGS only.
+    pub fn addRun(&mut self, configs: &[(&str, &str)]) -> &mut TestFile {
+        self.runs.push(
+            configs
+                .iter()
+                .map(|(key, val)| (key.to_string(), val.to_string()))
+                .collect(),
+        );
+        self
+    }
+
+    pub fn setDirectives(&mut self, directives: Directives) -> &mut TestFile {
+        self.directives = directives;
+        self
+    }
+
+    pub fn addFunction(&mut self, function: TestFunction) -> &mut TestFile {
+        self.functions.push(function);
+        self
+    }
+
+    /// Put `text`, the test printed one way or another, in a function of its
+    /// own taking the symbols of `test` as parameters.
+    pub fn addTest<T1, T2, T3, T4>(
+        &mut self,
+        test: &Test<T1, T2, T3, T4>,
+        text: String,
+    ) -> &mut TestFile
+    where
+        T1: Draw + Eval,
+        T2: Draw + Eval,
+        T3: Draw + Eval,
+        T4: Draw + Eval,
+    {
+        let name = format!("{}_{}", self.prefix, self.functions.len());
+        let mut function =
+            TestFunction::new(&name).uses(test.getConditional().as_ref(), &self.target);
+        for (Assertion, _) in test.getAssertions() {
+            function = function.uses(Assertion.as_ref(), &self.target);
+        }
+        self.addFunction(function.statement(text))
+    }
+
+    pub fn getFunctions(&self) -> &[TestFunction] {
+        &self.functions
+    }
+
+    /// The RUN lines of the file, continued over several lines.
+    fn runLines(&self) -> String {
+        let base = vec![];
+        let runs = if self.runs.is_empty() {
+            std::slice::from_ref(&base)
+        } else {
+            &self.runs[..]
+        };
+
+        let verify = match self.directives {
+            Directives::Omitted => "",
+            _ => " -verify",
+        };
+        let mut text = String::new();
+        for configs in runs {
+            text += &format!(
+                "// RUN: %clang_analyze_cc1 -triple {}{} %s \\\n\
+                 // RUN:   -analyzer-checker={}",
+                self.target, verify, CHECKERS
+            );
+            let configs = CONFIGS.iter().map(|(key, val)| (*key, *val)).chain(
+                configs
+                    .iter()
+                    .map(|(key, val)| (key.as_str(), val.as_str())),
+            );
+            for (key, val) in configs {
+                text += &format!(" \\\n// RUN:   -analyzer-config {}={}", key, val);
+            }
+            text += "\n";
+        }
+        text
+    }
+}
+
+impl fmt::Display for TestFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let functions: Vec<String> = self
+            .functions
+            .iter()
+            .map(|function| match self.directives {
+                Directives::Inline => function.to_string(),
+                Directives::Above => placeAbove(&function.to_string()),
+                Directives::Omitted => stripDirectives(&function.to_string()),
+            })
+            .collect();
+
+        write!(f, "{}", self.runLines())?;
+        // -verify fails on a file which expects nothing unless told so.
+        if self.directives != Directives::Omitted
+            && !functions
+                .iter()
+                .any(|function| function.contains("// expected-"))
+        {
+            write!(f, "\n// expected-no-diagnostics\n")?;
+        }
+        write!(
+            f,
+            "
+void clang_analyzer_eval(int);
+void clang_analyzer_warnIfReached(void);
+"
+        )?;
+        for function in functions {
+            write!(f, "\n{}\n", function)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::Fixture;
+
+    #[test]
+    fn test_TestFile() {
+        let fixture = Fixture::new(&[
+            ("a", CDataTypes::Int),
+            ("b", CDataTypes::UnsignedLong),
+            ("c", CDataTypes::Short),
+        ]);
+
+        let mut file = TestFile::new("foo", &fixture.target);
+        let first = fixture.test("a > 0 && b < 10", "a + 1 > a");
+        let second = fixture.test("c == 3", "c * a != 0");
+        file.addTest(&first, first.to_string())
+            .addTest(&second, second.to_string())
+            .addFunction(TestFunction::new("empty"));
+
+        let names: Vec<&str> = file.getFunctions().iter().map(|f| f.getName()).collect();
+        assert_eq!(names, ["foo_0", "foo_1", "empty"]);
+
+        let text = file.to_string();
+        assert!(text.starts_with(&format!(
+            "// RUN: %clang_analyze_cc1 -triple {} -verify %s \\
+// RUN:   -analyzer-checker=core,debug.ExprInspection \\
+// RUN:   -analyzer-config eagerly-assume=false
+\nvoid clang_analyzer_eval(int);",
+            fixture.target
+        )));
+        assert!(text.contains("\nvoid foo_0(int a, unsigned long b) {\n  if (a > 0 && b < 10) {"));
+        assert!(text.contains("\nvoid foo_1(short c, int a) {\n  if (c == 3) {"));
+        assert!(text.ends_with("  }\n}\n\nvoid empty(void) {\n}\n"));
+    }
+
+    #[test]
+    fn test_Directives() {
+        let fixture = Fixture::new(&[("x", CDataTypes::Int)]);
+        let test = fixture.test("x > 0", "x != 0");
+
+        let mut file = TestFile::new("foo", &fixture.target);
+        file.addTest(&test, test.to_string())
+            .addRun(&[])
+            .addRun(&[("support-symbolic-integer-casts", "true")])
+            .setDirectives(Directives::Above);
+        let text = file.to_string();
+        assert_eq!(text.matches("// RUN: %clang_analyze_cc1").count(), 2);
+        assert!(text.contains(
+            "eagerly-assume=false \\
+// RUN:   -analyzer-config support-symbolic-integer-casts=true
+"
+        ));
+        assert!(!text.contains("expected-no-diagnostics"));
+        assert!(text.contains(
+            "      // expected-warning@+1{{TRUE}}
+      clang_analyzer_eval(x != 0);
+"
+        ));
+        assert!(text.contains("      clang_analyzer_warnIfReached();\n"));
+
+        let mut empty = TestFile::new("foo", &fixture.target);
+        empty.addFunction(TestFunction::new("bar"));
+        assert!(empty.to_string().contains("\n// expected-no-diagnostics\n"));
+
+        file.setDirectives(Directives::Omitted);
+        let text = file.to_string();
+        assert!(!text.contains("-verify"));
+        assert!(!text.contains("expected-"));
+        assert!(text.contains("      clang_analyzer_eval(x != 0);\n"));
+    }
+}
@@ -0,0 +1,575 @@
+//! Transfer : Interval arithmetic over RangeSets for every unary and binary
+//! opcode.
+//!
+//! Given the ranges of both operands, the transfer function of an opcode
+//! returns a range containing every value `LHS op RHS` can evaluate to. The
+//! operands go through the same integer promotions and usual arithmetic
+//! conversions as in Eval, and the result wraps around to its C type.
+//! Operand values which make the operation undefined (division by zero,
+//! shifts by an out-of-range amount) are excluded from the result.
+//!
+//! Floating values are all the reals between the ends of each interval, as
+//! Eval only computes with their integers. Their sums, differences and
+//! products are bounded the same way as those of integers, quotients are not
+//! modeled.
+
+use crate::BinOp::Opcode;
+use crate::DataType::CDataTypes;
+use crate::Eval::Eval;
+use crate::Expr::{BinarySymExpr, Expr, UnarySymExpr};
+use crate::Range::{Constraints, RangeSet};
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::UnOp::UnaryOpcode;
+use std::fmt;
+
+/// Above this many interval pairs, operands are replaced by their hull.
+const MAX_PAIRS: usize = 64;
+
+/// An abstract value: every value an expression may take, and its type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbstractValue {
+    pub ty: CDataTypes,
+    pub range: RangeSet,
+}
+
+impl AbstractValue {
+    pub fn new(range: RangeSet, ty: CDataTypes) -> AbstractValue {
+        AbstractValue { ty, range }
+    }
+
+    /// Convert all values to another type.
+    pub fn cast(&self, ty: CDataTypes, target: &TargetInfo) -> AbstractValue {
+        AbstractValue::new(castRange(&self.range, ty, target), ty)
+    }
+}
+
+impl fmt::Display for AbstractValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}) {}", self.ty, self.range)
+    }
+}
+
+/// Convert a set of values to type `ty`, wrapping around the values which do
+/// not fit in it on `target`. Values converted to a floating type are rounded,
+/// which keeps their order.
+pub fn castRange(range: &RangeSet, ty: CDataTypes, target: &TargetInfo) -> RangeSet {
+    if !ty.isInteger() {
+        let ranges = range
+            .getRanges()
+            .iter()
+            .map(|(lo, hi)| (ty.wrap(*lo, target), ty.wrap(*hi, target)))
+            .collect();
+        return RangeSet::fromRanges(ranges);
+    }
+
+    let (T_MIN, T_MAX) = ty.getRange(target);
+    let span = 1i128 << ty.getWidth(target);
+    let mut ranges = vec![];
+    for (lo, hi) in range.getRanges() {
+        if T_MIN <= *lo && *hi <= T_MAX {
+            ranges.push((*lo, *hi));
+        } else if hi.abs_diff(*lo).saturating_add(1) >= span as u128 {
+            return RangeSet::full(ty, target);
+        } else {
+            let (wlo, whi) = (ty.wrap(*lo, target), ty.wrap(*hi, target));
+            if wlo <= whi {
+                ranges.push((wlo, whi));
+            } else {
+                ranges.push((wlo, T_MAX));
+                ranges.push((T_MIN, whi));
+            }
+        }
+    }
+
+    RangeSet::fromRanges(ranges)
+}
+
+/// Abstract counterpart of `evalBinary`.
+pub fn apply(
+    op: Opcode,
+    lhs: &AbstractValue,
+    rhs: &AbstractValue,
+    target: &TargetInfo,
+) -> AbstractValue {
+    let ty = op.getResultType(lhs.ty, rhs.ty, target);
+    let exact = applyExact(op, lhs, rhs, target);
+    AbstractValue::new(castRange(&exact, ty, target), ty)
+}
+
+/// Like `apply`, but returns the mathematical result of the operation before
+/// it is truncated to the result type. Comparing it against the limits of the
+/// result type tells whether the operation may overflow.
+pub fn applyExact(
+    op: Opcode,
+    lhs: &AbstractValue,
+    rhs: &AbstractValue,
+    target: &TargetInfo,
+) -> RangeSet {
+    if lhs.range.isEmpty() || rhs.range.isEmpty() {
+        return RangeSet::empty();
+    }
+
+    let ty = op.getResultType(lhs.ty, rhs.ty, target);
+    let floating = !lhs.ty.isInteger() || !rhs.ty.isInteger();
+    if floating && (op == Opcode::Div || op.needsIntegers()) {
+        return RangeSet::full(ty, target);
+    }
+
+    match op {
+        Opcode::LAnd | Opcode::LOr => {
+            let (lt, lf) = (canBeTrue(&lhs.range), lhs.range.contains(0));
+            let (rt, rf) = (canBeTrue(&rhs.range), rhs.range.contains(0));
+            let (canTrue, canFalse) = match op {
+                Opcode::LAnd => (lt && rt, lf || rf),
+                _ => (lt || rt, lf && rf),
+            };
+            return boolRange(canTrue, canFalse);
+        }
+        Opcode::Assign => return rhs.cast(lhs.ty, target).range,
+        Opcode::Shl | Opcode::Shr => {
+            // Only the valid shift amounts are kept.
+            let rty = rhs.ty.promote();
+            let amount = castRange(&rhs.range, rty, target)
+                .intersect(&RangeSet::new(0, ty.getWidth(target) as i128 - 1));
+            let value = castRange(&lhs.range, ty, target);
+            return pairwise(&value, &amount, |a, b| applyInterval(op, a, b, ty, target));
+        }
+        _ => {}
+    }
+
+    let common = lhs.ty.getCommonType(rhs.ty, target);
+    let (l, r) = (
+        castRange(&lhs.range, common, target),
+        castRange(&rhs.range, common, target),
+    );
+    match op {
+        Opcode::LT | Opcode::GT | Opcode::LE | Opcode::GE => {
+            let (lmin, lmax) = (l.getMin().unwrap(), l.getMax().unwrap());
+            let (rmin, rmax) = (r.getMin().unwrap(), r.getMax().unwrap());
+            let (always, never) = match op {
+                Opcode::LT => (lmax < rmin, lmin >= rmax),
+                Opcode::GT => (lmin > rmax, lmax <= rmin),
+                Opcode::LE => (lmax <= rmin, lmin > rmax),
+                _ => (lmin >= rmax, lmax < rmin),
+            };
+            boolRange(!never, !always)
+        }
+        Opcode::EQ | Opcode::NE => {
+            let same = l.getConcreteValue().is_some() && l == r;
+            let disjoint = l.intersect(&r).isEmpty();
+            match op {
+                Opcode::EQ => boolRange(!disjoint, !same),
+                _ => boolRange(!same, !disjoint),
+            }
+        }
+        _ => pairwise(&l, &r, |a, b| applyInterval(op, a, b, common, target)),
+    }
+}
+
+/// Type of `op operand` for an operand of type `ty`.
+pub fn getUnaryResultType(op: UnaryOpcode, ty: CDataTypes) -> CDataTypes {
+    match op {
+        UnaryOpcode::Neg | UnaryOpcode::Not => ty.promote(),
+        UnaryOpcode::LNot => CDataTypes::Int,
+        UnaryOpcode::Cast(ty) => ty,
+    }
+}
+
+/// Abstract counterpart of `evalUnary`.
+pub fn applyUnary(op: UnaryOpcode, operand: &AbstractValue, target: &TargetInfo) -> AbstractValue {
+    let ty = getUnaryResultType(op, operand.ty);
+    if operand.range.isEmpty() {
+        return AbstractValue::new(RangeSet::empty(), ty);
+    }
+
+    let range = match op {
+        UnaryOpcode::Not if !ty.isInteger() => RangeSet::full(ty, target),
+        UnaryOpcode::Neg | UnaryOpcode::Not => {
+            // ~v is -v - 1.
+            let offset = if op == UnaryOpcode::Not { -1 } else { 0 };
+            let ranges = castRange(&operand.range, ty, target)
+                .getRanges()
+                .iter()
+                .map(|(lo, hi)| (hi.saturating_neg() + offset, lo.saturating_neg() + offset))
+                .collect();
+            castRange(&RangeSet::fromRanges(ranges), ty, target)
+        }
+        UnaryOpcode::LNot => boolRange(operand.range.contains(0), canBeTrue(&operand.range)),
+        UnaryOpcode::Cast(ty) => castRange(&operand.range, ty, target),
+    };
+    AbstractValue::new(range, ty)
+}
+
+fn canBeTrue(range: &RangeSet) -> bool {
+    range
+        .getRanges()
+        .iter()
+        .any(|(lo, hi)| *lo != 0 || *hi != 0)
+}
+
+fn boolRange(canTrue: bool, canFalse: bool) -> RangeSet {
+    match (canTrue, canFalse) {
+        (true, true) => RangeSet::new(0, 1),
+        (true, false) => RangeSet::point(1),
+        (false, true) => RangeSet::point(0),
+        (false, false) => RangeSet::empty(),
+    }
+}
+
+/// Apply `f` on every pair of intervals of both sets and join the results.
+fn pairwise<F>(lhs: &RangeSet, rhs: &RangeSet, f: F) -> RangeSet
+where
+    F: Fn((i128, i128), (i128, i128)) -> RangeSet,
+{
+    let hull = |r: &RangeSet| match (r.getMin(), r.getMax()) {
+        (Some(lo), Some(hi)) => RangeSet::new(lo, hi),
+        _ => RangeSet::empty(),
+    };
+    let (lhs, rhs) = if lhs.getRanges().len() * rhs.getRanges().len() > MAX_PAIRS {
+        (hull(lhs), hull(rhs))
+    } else {
+        (lhs.clone(), rhs.clone())
+    };
+
+    let mut ret = RangeSet::empty();
+    for a in lhs.getRanges() {
+        for b in rhs.getRanges() {
+            ret = ret.union(&f(*a, *b));
+        }
+    }
+    ret
+}
+
+/// Smallest and largest of the given values, as a RangeSet. If any of them
+/// could not be computed (overflowed i128) all values of `ty` are returned.
+fn hullOf(vals: &[Option<i128>], ty: CDataTypes, target: &TargetInfo) -> RangeSet {
+    if vals.iter().any(|v| v.is_none()) {
+        return RangeSet::full(ty, target);
+    }
+
+    let vals: Vec<i128> = vals.iter().map(|v| v.unwrap()).collect();
+    RangeSet::new(*vals.iter().min().unwrap(), *vals.iter().max().unwrap())
+}
+
+/// All ones up to the most significant bit of `val`.
+fn bitmask(val: i128) -> i128 {
+    if val <= 0 {
+        0
+    } else {
+        (1i128 << (128 - val.leading_zeros())) - 1
+    }
+}
+
+/// Transfer function of arithmetic, bitwise and shift opcodes on two intervals
+/// already converted to type `ty`.
+fn applyInterval(
+    op: Opcode,
+    (a1, a2): (i128, i128),
+    (b1, b2): (i128, i128),
+    ty: CDataTypes,
+    target: &TargetInfo,
+) -> RangeSet {
+    match op {
+        Opcode::Add => hullOf(&[a1.checked_add(b1), a2.checked_add(b2)], ty, target),
+        Opcode::Sub => hullOf(&[a1.checked_sub(b2), a2.checked_sub(b1)], ty, target),
+        Opcode::Mul => hullOf(
+            &[
+                a1.checked_mul(b1),
+                a1.checked_mul(b2),
+                a2.checked_mul(b1),
+                a2.checked_mul(b2),
+            ],
+            ty,
+            target,
+        ),
+        Opcode::Div => {
+            // Division truncates towards zero, which is monotonic in both
+            // operands as long as the divisor does not change sign.
+            let mut ret = RangeSet::empty();
+            for (lo, hi) in [(b1, b2.min(-1)), (b1.max(1), b2)] {
+                if lo <= hi {
+                    ret = ret.union(&hullOf(
+                        &[Some(a1 / lo), Some(a1 / hi), Some(a2 / lo), Some(a2 / hi)],
+                        ty,
+                        target,
+                    ));
+                }
+            }
+            ret
+        }
+        Opcode::Rem => {
+            let divisors =
+                RangeSet::new(b1, b2).intersect(&RangeSet::point(0).complement(ty, target));
+            let (dmin, dmax) = match (divisors.getMin(), divisors.getMax()) {
+                (Some(lo), Some(hi)) => (lo, hi),
+                _ => return RangeSet::empty(),
+            };
+
+            // |a % b| < |b|, and the sign follows the dividend.
+            let smallest = if dmin <= 0 && 0 <= dmax {
+                1
+            } else {
+                dmin.abs().min(dmax.abs())
+            };
+            let largest = dmin.abs().max(dmax.abs()) - 1;
+            if a1 >= 0 && a2 < smallest {
+                return RangeSet::new(a1, a2);
+            }
+            if a2 <= 0 && -a1 < smallest {
+                return RangeSet::new(a1, a2);
+            }
+            RangeSet::new(a1.max(-largest).min(0), a2.min(largest).max(0))
+        }
+        Opcode::Shl => hullOf(
+            &[
+                a1.checked_mul(1i128 << b1),
+                a1.checked_mul(1i128 << b2),
+                a2.checked_mul(1i128 << b1),
+                a2.checked_mul(1i128 << b2),
+            ],
+            ty,
+            target,
+        ),
+        Opcode::Shr => hullOf(
+            &[
+                Some(a1 >> b1),
+                Some(a1 >> b2),
+                Some(a2 >> b1),
+                Some(a2 >> b2),
+            ],
+            ty,
+            target,
+        ),
+        Opcode::And | Opcode::Or | Opcode::Xor => {
+            if a1 == a2 && b1 == b2 {
+                return RangeSet::point(match op {
+                    Opcode::And => a1 & b1,
+                    Opcode::Or => a1 | b1,
+                    _ => a1 ^ b1,
+                });
+            }
+            if a1 < 0 || b1 < 0 {
+                return RangeSet::full(ty, target);
+            }
+
+            match op {
+                Opcode::And => RangeSet::new(0, a2.min(b2)),
+                Opcode::Or => RangeSet::new(a1.max(b1), bitmask(a2.max(b2))),
+                _ => RangeSet::new(0, bitmask(a2.max(b2))),
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Expressions which can be evaluated over ranges of their symbols.
+pub trait EvalRange: Eval {
+    /// Returns every value the expression may take on `target` when its
+    /// symbols are within `ranges`.
+    fn evalRange(&self, ranges: &Constraints, target: &TargetInfo) -> AbstractValue;
+}
+
+impl EvalRange for Sym {
+    fn evalRange(&self, ranges: &Constraints, target: &TargetInfo) -> AbstractValue {
+        AbstractValue::new(ranges.getRangeOf(self, target), self.ty)
+    }
+}
+
+impl EvalRange for Conc {
+    fn evalRange(&self, _ranges: &Constraints, _target: &TargetInfo) -> AbstractValue {
+        AbstractValue::new(RangeSet::point(self.val), self.ty)
+    }
+}
+
+impl EvalRange for Expr {
+    fn evalRange(&self, ranges: &Constraints, target: &TargetInfo) -> AbstractValue {
+        match self {
+            Expr::Sym(sym) => sym.evalRange(ranges, target),
+            Expr::Conc(conc) => conc.evalRange(ranges, target),
+            Expr::Binary(bse) => bse.evalRange(ranges, target),
+            Expr::Unary(unary) => unary.evalRange(ranges, target),
+        }
+    }
+}
+
+impl<T> EvalRange for UnarySymExpr<T>
+where
+    T: EvalRange,
+{
+    fn evalRange(&self, ranges: &Constraints, target: &TargetInfo) -> AbstractValue {
+        let operand = self.getOperand().evalRange(ranges, target);
+        applyUnary(self.getOpcode(), &operand, target)
+    }
+}
+
+impl<T1, T2> EvalRange for BinarySymExpr<T1, T2>
+where
+    T1: EvalRange,
+    T2: EvalRange,
+{
+    fn evalRange(&self, ranges: &Constraints, target: &TargetInfo) -> AbstractValue {
+        apply(
+            self.getOpcode(),
+            &self.getLHS().evalRange(ranges, target),
+            &self.getRHS().evalRange(ranges, target),
+            target,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Range::Constrain;
+
+    fn val(lo: i128, hi: i128, ty: CDataTypes) -> AbstractValue {
+        AbstractValue::new(RangeSet::new(lo, hi), ty)
+    }
+
+    #[test]
+    fn test_applyUnary() {
+        let target = TargetInfo::default();
+        let int = CDataTypes::Int;
+        let uint = CDataTypes::UnsignedInt;
+        let uch = CDataTypes::UnsignedChar;
+
+        let r = applyUnary(UnaryOpcode::Neg, &val(-3, 5, int), &target);
+        assert_eq!(r, val(-5, 3, int));
+        let r = applyUnary(UnaryOpcode::Neg, &val(0, 1, uint), &target);
+        assert_eq!(
+            r.range.getRanges(),
+            &[(0, 0), (u32::MAX as i128, u32::MAX as i128)]
+        );
+        let r = applyUnary(UnaryOpcode::Not, &val(0, 255, uch), &target);
+        assert_eq!(r, val(-256, -1, int));
+
+        assert_eq!(
+            applyUnary(UnaryOpcode::LNot, &val(1, 9, int), &target),
+            val(0, 0, int)
+        );
+        assert_eq!(
+            applyUnary(UnaryOpcode::LNot, &val(0, 9, int), &target),
+            val(0, 1, int)
+        );
+
+        let r = applyUnary(UnaryOpcode::Cast(uch), &val(250, 260, int), &target);
+        assert_eq!(r.range.getRanges(), &[(0, 4), (250, 255)]);
+        assert_eq!(r.ty, uch);
+    }
+
+    #[test]
+    fn test_applyArithmetic() {
+        let target = TargetInfo::default();
+        let int = CDataTypes::Int;
+        let uint = CDataTypes::UnsignedInt;
+        let uch = CDataTypes::UnsignedChar;
+
+        // Narrow operands are promoted, nothing wraps.
+        let r = apply(Opcode::Add, &val(250, 255, uch), &val(10, 20, uch), &target);
+        assert_eq!(r, val(260, 275, int));
+
+        // Unsigned arithmetic wraps around.
+        let r = apply(
+            Opcode::Add,
+            &val(u32::MAX as i128 - 1, u32::MAX as i128, uint),
+            &val(1, 2, uint),
+            &target,
+        );
+        assert_eq!(
+            r.range.getRanges(),
+            &[(0, 1), (u32::MAX as i128, u32::MAX as i128)]
+        );
+
+        // Signed overflow is visible in the exact result.
+        let max = val(i32::MAX as i128, i32::MAX as i128, int);
+        let exact = applyExact(Opcode::Add, &max, &val(1, 1, int), &target);
+        assert_eq!(exact.getMax(), Some(i32::MAX as i128 + 1));
+
+        let r = apply(Opcode::Mul, &val(-3, 2, int), &val(-5, 4, int), &target);
+        assert_eq!(r, val(-12, 15, int));
+
+        let r = apply(Opcode::Div, &val(-10, 10, int), &val(-2, 5, int), &target);
+        assert_eq!(r, val(-10, 10, int));
+        let r = apply(Opcode::Div, &val(10, 20, int), &val(0, 0, int), &target);
+        assert!(r.range.isEmpty());
+
+        let r = apply(Opcode::Rem, &val(0, 30, uint), &val(0, 50, uint), &target);
+        assert_eq!(r, val(0, 30, uint));
+        let r = apply(Opcode::Rem, &val(-100, 100, int), &val(7, 7, int), &target);
+        assert_eq!(r, val(-6, 6, int));
+    }
+
+    #[test]
+    fn test_applyBitwiseAndShifts() {
+        let target = TargetInfo::default();
+        let int = CDataTypes::Int;
+        let sh = CDataTypes::Short;
+
+        let r = apply(Opcode::And, &val(0, 12, int), &val(5, 100, int), &target);
+        assert_eq!(r, val(0, 12, int));
+        let r = apply(Opcode::Or, &val(1, 12, int), &val(5, 6, int), &target);
+        assert_eq!(r, val(5, 15, int));
+        let r = apply(Opcode::Xor, &val(-1, 1, int), &val(5, 6, int), &target);
+        assert_eq!(r.range, RangeSet::full(int, &target));
+
+        let r = apply(Opcode::Shl, &val(1, 3, sh), &val(2, 40, int), &target);
+        assert_eq!(r, val(4, 3 << 31, int).cast(int, &target));
+        let r = apply(Opcode::Shr, &val(-16, 16, sh), &val(2, 3, int), &target);
+        assert_eq!(r, val(-4, 4, int));
+    }
+
+    #[test]
+    fn test_applyComparisons() {
+        let target = TargetInfo::default();
+        let int = CDataTypes::Int;
+        let uint = CDataTypes::UnsignedInt;
+
+        assert_eq!(
+            apply(Opcode::LT, &val(0, 5, int), &val(6, 9, int), &target),
+            val(1, 1, int)
+        );
+        assert_eq!(
+            apply(Opcode::GE, &val(0, 5, int), &val(5, 9, int), &target),
+            val(0, 1, int)
+        );
+        assert_eq!(
+            apply(Opcode::EQ, &val(3, 3, int), &val(3, 3, int), &target),
+            val(1, 1, int)
+        );
+        assert_eq!(
+            apply(Opcode::NE, &val(0, 2, int), &val(3, 9, int), &target),
+            val(1, 1, int)
+        );
+
+        // -1 converts to UINT_MAX.
+        assert_eq!(
+            apply(Opcode::LT, &val(-1, -1, int), &val(0, 9, uint), &target),
+            val(0, 0, int)
+        );
+
+        assert_eq!(
+            apply(Opcode::LAnd, &val(0, 0, int), &val(0, 9, int), &target),
+            val(0, 0, int)
+        );
+        assert_eq!(
+            apply(Opcode::LOr, &val(1, 2, int), &val(0, 9, int), &target),
+            val(1, 1, int)
+        );
+    }
+
+    #[test]
+    fn test_evalRange() {
+        let target = TargetInfo::default();
+        let a = Sym::new("a", "unsigned int");
+        let b = Sym::new("b", "unsigned int");
+        let c50 = Conc::new(50, CDataTypes::Int);
+        let c30 = Conc::new(30, CDataTypes::Int);
+        let cond = BinarySymExpr::new(&a, &c30, Opcode::LE);
+        let rem = BinarySymExpr::new(&a, &b, Opcode::Rem);
+        let lt = BinarySymExpr::new(&rem, &c50, Opcode::LT);
+
+        let ranges = cond.constraints(&target);
+        assert_eq!(lt.evalRange(&ranges, &target), val(1, 1, CDataTypes::Int));
+    }
+}
@@ -0,0 +1,44 @@
+//! UnOp : Collect unary operations available in C, along with explicit casts.
+
+use crate::DataType::CDataTypes;
+use std::fmt;
+
+/// Unary opcodes which UnarySymExpr are allowed to take into account.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UnaryOpcode {
+    /// Unary minus, `-x`.
+    Neg,
+    /// Bitwise not, `~x`.
+    Not,
+    /// Logical not, `!x`.
+    LNot,
+    /// Explicit conversion, `(type)x`.
+    Cast(CDataTypes),
+}
+
+impl UnaryOpcode {
+    /// Returns the unary opcode spelled `opcode`, either an operator or a
+    /// parenthesized type name.
+    pub fn getOpcode(opcode: &str) -> Option<UnaryOpcode> {
+        match opcode {
+            "-" => Some(Self::Neg),
+            "~" => Some(Self::Not),
+            "!" => Some(Self::LNot),
+            _ => {
+                let ty = opcode.strip_prefix('(')?.strip_suffix(')')?;
+                CDataTypes::getType(ty.trim()).map(Self::Cast)
+            }
+        }
+    }
+}
+
+impl fmt::Display for UnaryOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOpcode::Neg => write!(f, "-"),
+            UnaryOpcode::Not => write!(f, "~"),
+            UnaryOpcode::LNot => write!(f, "!"),
+            UnaryOpcode::Cast(ty) => write!(f, "({})", ty),
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Undefined : Find the operations of an expression whose behavior may be
+//! undefined.
+//!
+//! An operation is looked at with the ranges its operands take, see Transfer.
+//! When some of those values make it undefined (a zero divisor, `INT_MIN / -1`,
+//! an out-of-range shift amount, signed overflow) the analyzer is free to do
+//! anything on that path, so the expected result of a test is meaningless. When
+//! all of them do, the analyzer reports it with one of its core checkers,
+//! which a test can expect instead.
+//!
+//! # Examples
+//! ```
+//! use symi::BinOp::Opcode;
+//! use symi::DataType::CDataTypes;
+//! use symi::Expr::BinarySymExpr;
+//! use symi::Range::Constraints;
+//! use symi::Symbol::{Conc, Sym};
+//! use symi::Target::TargetInfo;
+//! use symi::Undefined::{CheckUB, UBKind};
+//!
+//! let x = Sym::new("x", "int");
+//! let div = BinarySymExpr::new(&Conc::new(10, CDataTypes::Int), &x, Opcode::Div);
+//! let mut hazards = vec![];
+//! div.hazards(&Constraints::new(), &mut hazards, &TargetInfo::default());
+//! assert_eq!(hazards[0].kind, UBKind::DivByZero);
+//! assert!(!hazards[0].always);
+//! ```
+
+use crate::BinOp::Opcode;
+use crate::Expr::{BinarySymExpr, Expr, UnarySymExpr};
+use crate::Print::{Parens, Print};
+use crate::Range::{Constrain, Constraints, RangeSet};
+use crate::Symbol::{Conc, Sym};
+use crate::Target::TargetInfo;
+use crate::Transfer::{applyExact, castRange, EvalRange};
+use crate::UnOp::UnaryOpcode;
+use std::fmt;
+
+/// Kinds of undefined behavior of integer operations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UBKind {
+    /// Right operand of `/` or `%` is zero.
+    DivByZero,
+    /// `MIN / -1` or `MIN % -1` in a signed type.
+    DivOverflow,
+    /// Shift amount is negative or not less than the width of the type.
+    InvalidShift,
+    /// Result of a signed `+`, `-`, `*` or unary `-` does not fit its type.
+    SignedOverflow,
+}
+
+impl UBKind {
+    /// The analyzer checker reporting this kind of UB, if any.
+    pub fn getChecker(&self) -> Option<&'static str> {
+        match self {
+            UBKind::DivByZero => Some("core.DivideZero"),
+            UBKind::InvalidShift => Some("core.UndefinedBinaryOperatorResult"),
+            UBKind::DivOverflow | UBKind::SignedOverflow => None,
+        }
+    }
+
+    /// Part of the checker's warning, enough for -verify to match it. Clang
+    /// versions word the shift warnings differently, but all mention a shift.
+    pub fn getDiagnostic(&self) -> Option<&'static str> {
+        match self {
+            UBKind::DivByZero => Some("Division by zero"),
+            UBKind::InvalidShift => Some("shift"),
+            UBKind::DivOverflow | UBKind::SignedOverflow => None,
+        }
+    }
+}
+
+impl fmt::Display for UBKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UBKind::DivByZero => write!(f, "division by zero"),
+            UBKind::DivOverflow => write!(f, "signed division overflow"),
+            UBKind::InvalidShift => write!(f, "shift amount out of range"),
+            UBKind::SignedOverflow => write!(f, "signed overflow"),
+        }
+    }
+}
+
+/// An operation which is undefined for some of its operand values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hazard {
+    pub kind: UBKind,
+    /// Whether it is undefined for all of them.
+    pub always: bool,
+    /// The operation, as C.
+    pub expr: String,
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let when = if self.always { "always" } else { "maybe" };
+        write!(f, "{}: {} ({})", self.expr, self.kind, when)
+    }
+}
+
+/// Expressions whose operations can be checked for undefined behavior.
+pub trait CheckUB: EvalRange + Constrain + Print {
+    /// Push the operations of the expression which may be undefined on
+    /// `target` when its symbols are within `ranges`, operands before the
+    /// operations using them.
+    fn hazards(&self, ranges: &Constraints, out: &mut Vec<Hazard>, target: &TargetInfo);
+}
+
+/// Returns None if none of the values of `range` are in `bad`, otherwise
+/// whether all of them are.
+fn overlap(range: &RangeSet, bad: &RangeSet) -> Option<bool> {
+    let hit = range.intersect(bad);
+    if hit.isEmpty() {
+        None
+    } else {
+        Some(hit == *range)
+    }
+}
+
+fn push<T: Print>(out: &mut Vec<Hazard>, expr: &T, kind: UBKind, found: Option<bool>) {
+    if let Some(always) = found {
+        out.push(Hazard {
+            kind,
+            always,
+            expr: expr.print(Parens::Minimal),
+        });
+    }
+}
+
+impl CheckUB for Sym {
+    fn hazards(&self, _ranges: &Constraints, _out: &mut Vec<Hazard>, _target: &TargetInfo) {}
+}
+
+impl CheckUB for Conc {
+    fn hazards(&self, _ranges: &Constraints, _out: &mut Vec<Hazard>, _target: &TargetInfo) {}
+}
+
+impl CheckUB for Expr {
+    fn hazards(&self, ranges: &Constraints, out: &mut Vec<Hazard>, target: &TargetInfo) {
+        match self {
+            Expr::Sym(sym) => sym.hazards(ranges, out, target),
+            Expr::Conc(conc) => conc.hazards(ranges, out, target),
+            Expr::Binary(bse) => bse.hazards(ranges, out, target),
+            Expr::Unary(unary) => unary.hazards(ranges, out, target),
+        }
+    }
+}
+
+impl<T> CheckUB for UnarySymExpr<T>
+where
+    T: CheckUB,
+{
+    fn hazards(&self, ranges: &Constraints, out: &mut Vec<Hazard>, target: &TargetInfo) {
+        self.getOperand().hazards(ranges, out, target);
+
+        let operand = self.getOperand().evalRange(ranges, target);
+        let ty = operand.ty.promote();
+        if self.getOpcode() == UnaryOpcode::Neg && ty.isInteger() && ty.isSigned(target) {
+            let found = overlap(
+                &castRange(&operand.range, ty, target),
+                &RangeSet::point(ty.getMin(target)),
+            );
+            push(out, self, UBKind::SignedOverflow, found);
+        }
+    }
+}
+
+impl<T1, T2> CheckUB for BinarySymExpr<T1, T2>
+where
+    T1: CheckUB,
+    T2: CheckUB,
+{
+    fn hazards(&self, ranges: &Constraints, out: &mut Vec<Hazard>, target: &TargetInfo) {
+        let op = self.getOpcode();
+        self.getLHS().hazards(ranges, out, target);
+        // The RHS of `&&` is only evaluated when the LHS holds, the one of `||`
+        // when it does not.
+        match op {
+            Opcode::LAnd => {
+                let ranges = ranges.and(&self.getLHS().constraints(target));
+                self.getRHS().hazards(&ranges, out, target);
+            }
+            Opcode::LOr => {
+                let ranges = ranges.and(&self.getLHS().negatedConstraints(target));
+                self.getRHS().hazards(&ranges, out, target);
+            }
+            _ => self.getRHS().hazards(ranges, out, target),
+        }
+
+        let (lhs, rhs) = (
+            self.getLHS().evalRange(ranges, target),
+            self.getRHS().evalRange(ranges, target),
+        );
+        if !lhs.ty.isInteger() || !rhs.ty.isInteger() {
+            return;
+        }
+
+        let common = lhs.ty.getCommonType(rhs.ty, target);
+        let (l, r) = (
+            castRange(&lhs.range, common, target),
+            castRange(&rhs.range, common, target),
+        );
+        match op {
+            Opcode::Div | Opcode::Rem => {
+                push(
+                    out,
+                    self,
+                    UBKind::DivByZero,
+                    overlap(&r, &RangeSet::point(0)),
+                );
+                if common.isSigned(target) {
+                    let (min, minusOne) =
+                        (RangeSet::point(common.getMin(target)), RangeSet::point(-1));
+                    let found = match (overlap(&l, &min), overlap(&r, &minusOne)) {
+                        (Some(lAlways), Some(rAlways)) => Some(lAlways && rAlways),
+                        _ => None,
+                    };
+                    push(out, self, UBKind::DivOverflow, found);
+                }
+            }
+            Opcode::Shl | Opcode::Shr => {
+                let ty = rhs.ty.promote();
+                let width = lhs.ty.promote().getWidth(target) as i128;
+                let valid = RangeSet::new(0, width - 1);
+                let found = overlap(
+                    &castRange(&rhs.range, ty, target),
+                    &valid.complement(ty, target),
+                );
+                push(out, self, UBKind::InvalidShift, found);
+            }
+            Opcode::Add | Opcode::Sub | Opcode::Mul if common.isSigned(target) => {
+                let exact = applyExact(op, &lhs, &rhs, target);
+                let fits = exact.intersect(&RangeSet::full(common, target));
+                let found = match (fits.isEmpty(), fits == exact) {
+                    (_, true) => None,
+                    (always, false) => Some(always),
+                };
+                push(out, self, UBKind::SignedOverflow, found);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::Fixture;
+    use crate::DataType::CDataTypes;
+
+    #[test]
+    fn test_hazards() {
+        let fixture = Fixture::new(&[("x", CDataTypes::Int), ("u", CDataTypes::UnsignedChar)]);
+        let hazards = |src: &str, ranges: &Constraints| {
+            let mut out = vec![];
+            fixture
+                .parse(src)
+                .hazards(ranges, &mut out, &fixture.target);
+            out.iter().map(|h| h.to_string()).collect::<Vec<_>>()
+        };
+        let none = Constraints::new();
+
+        assert!(hazards("u + 1 < x / 2", &none).is_empty());
+        assert_eq!(hazards("x / u", &none), ["x / u: division by zero (maybe)"]);
+        assert!(hazards("u != 0 && x / u", &none).is_empty());
+        assert!(hazards("u == 0 || x / u", &none).is_empty());
+        assert!(hazards("!u || x / u", &none).is_empty());
+        assert_eq!(
+            hazards("u != 0 || x / u", &none),
+            ["x / u: division by zero (always)"]
+        );
+        // Ranges do not know that x - x is 0.
+        assert_eq!(
+            hazards("u / (x - x)", &none),
+            [
+                "x - x: signed overflow (maybe)",
+                "u / (x - x): division by zero (maybe)",
+            ]
+        );
+        assert_eq!(
+            hazards("x % 0", &none),
+            ["x % 0: division by zero (always)"]
+        );
+        assert_eq!(
+            hazards("x / -1 + -x", &none),
+            [
+                "x / -1: signed division overflow (maybe)",
+                "-x: signed overflow (maybe)",
+                "x / -1 + -x: signed overflow (maybe)",
+            ]
+        );
+        assert_eq!(
+            hazards("1 << u", &none),
+            ["1 << u: shift amount out of range (maybe)"]
+        );
+        assert!(hazards("1LL << u % 64", &none).is_empty());
+        assert_eq!(
+            hazards("x >> -1", &none),
+            ["x >> -1: shift amount out of range (always)"]
+        );
+
+        // Within the ranges of a Conditional.
+        let ranges = fixture
+            .parse("x > 2147483000 && u >= 32")
+            .constraints(&fixture.target);
+        assert_eq!(
+            hazards("x + 1000 < x << u", &ranges),
+            [
+                "x + 1000: signed overflow (always)",
+                "x << u: shift amount out of range (always)",
+            ]
+        );
+        assert!(hazards("u * 2 - 64", &ranges).is_empty());
+    }
+}
@@ -26,15 +26,74 @@
 // fuzzer requires two components: symbolic representation, and probabilistic
 // methods to fuzz those symbols. symi should be the one true representation of
 // symbols. Every other crate can use it to generate symbols in a determined
-// manner. And these other crates can also pass a probabilistic model (how?)
-// according to which symi will generate these symbols.
+// manner. And these other crates can also pass a probabilistic model, the
+// Weights of a Generator, according to which symi will generate these symbols.
 
 pub mod BinOp;
+pub mod Corner;
 pub mod DataType;
 pub mod Draw;
+pub mod Eval;
 pub mod Expr;
+pub mod Generator;
+pub mod Oracle;
+pub mod Parse;
+pub mod Print;
+pub mod Range;
+pub mod Smt;
+pub mod Strategy;
 pub mod Symbol;
+pub mod Target;
 pub mod Test;
+pub mod TestFile;
+pub mod Transfer;
+pub mod UnOp;
+pub mod Undefined;
+
+/// Helpers shared by the tests of the modules.
+#[cfg(test)]
+mod testutil {
+    use crate::DataType::CDataTypes;
+    use crate::Expr::{BinarySymExpr, Expr};
+    use crate::Parse::{parse, SymbolTable};
+    use crate::Target::TargetInfo;
+    use crate::Test::{DynTest, Test};
+    use std::sync::Arc;
+
+    /// Symbols and a target to parse the expressions of a test with.
+    pub(crate) struct Fixture {
+        pub symbols: SymbolTable,
+        pub target: TargetInfo,
+    }
+
+    impl Fixture {
+        /// Returns a fixture of `symbols` on the default target.
+        pub fn new(symbols: &[(&str, CDataTypes)]) -> Fixture {
+            Fixture {
+                symbols: symbols
+                    .iter()
+                    .map(|(name, ty)| (name.to_string(), *ty))
+                    .collect(),
+                target: TargetInfo::default(),
+            }
+        }
+
+        /// Parse `src`, which has to be valid.
+        pub fn parse(&self, src: &str) -> Arc<Expr> {
+            parse(src, &self.symbols, &self.target).unwrap()
+        }
+
+        /// Parse `src`, which has to be a binary operation.
+        pub fn binary(&self, src: &str) -> Arc<BinarySymExpr<Expr, Expr>> {
+            Arc::clone(self.parse(src).asBinary().unwrap())
+        }
+
+        /// Returns the test of `assert` under `cond`.
+        pub fn test(&self, cond: &str, assert: &str) -> Arc<DynTest> {
+            Test::new(&self.binary(cond), &self.binary(assert), &self.target)
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -42,10 +101,11 @@ mod tests {
 
     #[test]
     fn test_symi() {
+        let target = Target::TargetInfo::default();
         let sym = Symbol::Sym::new("x", "unsigned int");
         let conc = Symbol::Conc::new(42, DataType::CDataTypes::Int);
         let bse = Expr::BinarySymExpr::new(&sym, &conc, BinOp::Opcode::LAnd);
         let bse2 = Expr::BinarySymExpr::new(&bse, &sym, BinOp::Opcode::Mul);
-        let _test = Test::Test::new(&bse, &bse2);
+        let _test = Test::Test::new(&bse, &bse2, &target);
     }
 }